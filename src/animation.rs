@@ -0,0 +1,505 @@
+use bevy::{asset::LoadContext, prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::ron_asset::{RonAsset, RonAssetError, RonAssetLoader};
+
+// Frame based sprite animations by name. Sprite sheets are cut into a grid of cells, an animation
+// lists the cells it shows, for how long and how it plays on. Loaded from
+// assets/animations/default.animations.ron, see ron_asset.
+//
+// Anything with a SpriteAnimation component plays: it gets the sheet's texture and a TextureAtlas once
+// the library is loaded, and shows up then if it was spawned hidden. Frames can name events, sent as
+// SpriteAnimationEvent when the frame comes up, and animations that don't loop send
+// SpriteAnimationFinished when done.
+pub const ANIMATION_LIBRARY_PATH: &str = "animations/default.animations.ron";
+
+// shorter frames would make a loop spin in place, see animate_sprites
+const MIN_FRAME_SECS: f32 = 0.001;
+
+#[derive(Asset, TypePath, Debug)]
+pub struct SpriteAnimations {
+    pub animations: HashMap<String, SpriteClip>,
+}
+
+impl SpriteAnimations {
+    pub fn get(&self, name: &str) -> Option<&SpriteClip> {
+        self.animations.get(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct SpriteClip {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    // never empty
+    pub frames: Vec<SpriteFrame>,
+    pub mode: AnimationMode,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpriteFrame {
+    // cell of the sheet, row by row from the top left
+    pub index: usize,
+    pub secs: f32,
+    pub event: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    #[default]
+    Loop,
+    // stops on the last frame
+    Once,
+    // back and forth, without repeating the first and last frames
+    PingPong,
+}
+
+#[derive(Deserialize)]
+struct SheetDef {
+    texture: String,
+    cell: (f32, f32),
+    columns: usize,
+    rows: usize,
+    #[serde(default)]
+    padding: Option<(f32, f32)>,
+    #[serde(default)]
+    offset: Option<(f32, f32)>,
+}
+
+// Frames are cell indices. Durations and events are keyed by position in frames, frames without a
+// duration last frame_secs
+#[derive(Deserialize)]
+struct AnimationDef {
+    sheet: String,
+    frames: Vec<usize>,
+    frame_secs: f32,
+    #[serde(default)]
+    durations: HashMap<usize, f32>,
+    #[serde(default)]
+    mode: AnimationMode,
+    #[serde(default)]
+    events: HashMap<usize, String>,
+}
+
+#[derive(Deserialize)]
+pub struct SpriteAnimationsDef {
+    sheets: HashMap<String, SheetDef>,
+    animations: HashMap<String, AnimationDef>,
+}
+
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct AnimationLibrary(pub Handle<SpriteAnimations>);
+
+impl RonAsset for SpriteAnimations {
+    type Def = SpriteAnimationsDef;
+
+    const EXTENSIONS: &'static [&'static str] = &["animations.ron"];
+
+    fn from_def(
+        def: SpriteAnimationsDef,
+        load_context: &mut LoadContext,
+    ) -> Result<Self, RonAssetError> {
+        // every sheet once, its layout as a labeled sub-asset
+        let mut sheets = HashMap::new();
+        for (name, sheet) in def.sheets {
+            let layout = TextureAtlasLayout::from_grid(
+                Vec2::from(sheet.cell),
+                sheet.columns,
+                sheet.rows,
+                sheet.padding.map(Vec2::from),
+                sheet.offset.map(Vec2::from),
+            );
+            let n_cells = sheet.columns * sheet.rows;
+            let texture = load_context.load(sheet.texture);
+            let layout = load_context.add_labeled_asset(format!("sheet/{}", name), layout);
+            sheets.insert(name, (texture, layout, n_cells));
+        }
+
+        let animations = build_clips(def.animations, &sheets)?;
+        Ok(SpriteAnimations { animations })
+    }
+}
+
+// Texture, layout and number of cells of a sheet, by name
+type LoadedSheets = HashMap<String, (Handle<Image>, Handle<TextureAtlasLayout>, usize)>;
+
+// Checks every animation against its sheet and turns it into the clip played
+fn build_clips(
+    defs: HashMap<String, AnimationDef>,
+    sheets: &LoadedSheets,
+) -> Result<HashMap<String, SpriteClip>, RonAssetError> {
+    let mut animations = HashMap::new();
+    for (name, animation) in defs {
+        let Some((texture, layout, n_cells)) = sheets.get(&animation.sheet) else {
+            return Err(RonAssetError::Invalid(format!(
+                "animation {} uses unknown sheet {}",
+                name, animation.sheet
+            )));
+        };
+        if animation.frames.is_empty() {
+            return Err(RonAssetError::Invalid(format!(
+                "animation {} has no frames",
+                name
+            )));
+        }
+        if let Some(&index) = animation.frames.iter().find(|&&index| index >= *n_cells) {
+            return Err(RonAssetError::Invalid(format!(
+                "animation {} frame {} is outside its sheet",
+                name, index
+            )));
+        }
+        let frames = animation
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| SpriteFrame {
+                index,
+                secs: animation
+                    .durations
+                    .get(&position)
+                    .copied()
+                    .unwrap_or(animation.frame_secs)
+                    .max(MIN_FRAME_SECS),
+                event: animation.events.get(&position).cloned(),
+            })
+            .collect();
+        let clip = SpriteClip {
+            texture: texture.clone(),
+            layout: layout.clone(),
+            frames,
+            mode: animation.mode,
+        };
+        animations.insert(name, clip);
+    }
+    Ok(animations)
+}
+
+// Plays the named animation from its first frame, on an entity spawned with a SpriteBundle
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+    pub name: String,
+    // playback rate, 2. plays twice as fast
+    pub speed: f32,
+    pub despawn_on_finish: bool,
+    position: usize,
+    elapsed: f32,
+    is_forward: bool,
+    is_finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            speed: 1.,
+            despawn_on_finish: false,
+            position: 0,
+            elapsed: 0.,
+            is_forward: true,
+            is_finished: false,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    // once it's done, despawns its entity with its children. Never happens to looping animations
+    pub fn with_despawn_on_finish(mut self) -> Self {
+        self.despawn_on_finish = true;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    // position in the animation's frames, not the sheet's cell index
+    pub fn frame(&self) -> usize {
+        self.position
+    }
+
+    // the position after this one, None when an animation that plays once is over
+    fn next_position(&mut self, n_frames: usize, mode: AnimationMode) -> Option<usize> {
+        let last = n_frames - 1;
+        match mode {
+            AnimationMode::Loop => Some((self.position + 1) % n_frames),
+            AnimationMode::Once => (self.position < last).then_some(self.position + 1),
+            AnimationMode::PingPong if last == 0 => Some(0),
+            AnimationMode::PingPong => {
+                if self.is_forward && self.position >= last {
+                    self.is_forward = false;
+                } else if !self.is_forward && self.position == 0 {
+                    self.is_forward = true;
+                }
+                match self.is_forward {
+                    true => Some((self.position + 1).min(last)),
+                    false => Some(self.position - 1),
+                }
+            }
+        }
+    }
+}
+
+// A frame that names an event came up
+#[derive(Event, Clone, Debug)]
+pub struct SpriteAnimationEvent {
+    pub entity: Entity,
+    pub animation: String,
+    pub frame: usize,
+    pub event: String,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct SpriteAnimationFinished {
+    pub entity: Entity,
+    pub animation: String,
+}
+
+pub fn animation_plugin(app: &mut App) {
+    app.init_asset::<SpriteAnimations>()
+        .init_asset_loader::<RonAssetLoader<SpriteAnimations>>()
+        .add_event::<SpriteAnimationEvent>()
+        .add_event::<SpriteAnimationFinished>()
+        .add_systems(Startup, load_animation_library)
+        .add_systems(
+            Update,
+            (
+                refresh_sprite_animations,
+                attach_sprite_animations,
+                animate_sprites,
+            )
+                .chain(),
+        );
+}
+
+pub fn load_animation_library(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(AnimationLibrary(asset_server.load(ANIMATION_LIBRARY_PATH)));
+}
+
+// Gives new animations their sheet, set to the first frame
+pub fn attach_sprite_animations(
+    mut cmd: Commands,
+    library: Res<AnimationLibrary>,
+    animations: Res<Assets<SpriteAnimations>>,
+    mut evw_events: EventWriter<SpriteAnimationEvent>,
+    mut q_animations: Query<(Entity, &SpriteAnimation, &mut Visibility), Without<TextureAtlas>>,
+) {
+    let Some(animations) = animations.get(&library.0) else {
+        return;
+    };
+    for (entity, animation, mut visibility) in &mut q_animations {
+        let Some(clip) = animations.get(&animation.name) else {
+            warn!("no sprite animation named {}", animation.name);
+            // don't warn again every frame
+            cmd.entity(entity).remove::<SpriteAnimation>();
+            continue;
+        };
+        cmd.entity(entity).insert((
+            clip.texture.clone(),
+            TextureAtlas {
+                layout: clip.layout.clone(),
+                index: clip.frames[0].index,
+            },
+        ));
+        if *visibility == Visibility::Hidden {
+            *visibility = Visibility::Inherited;
+        }
+        if let Some(event) = &clip.frames[0].event {
+            evw_events.send(SpriteAnimationEvent {
+                entity,
+                animation: animation.name.clone(),
+                frame: 0,
+                event: event.clone(),
+            });
+        }
+    }
+}
+
+// Points animations already playing at a reloaded library's sheets
+pub fn refresh_sprite_animations(
+    mut evr_assets: EventReader<AssetEvent<SpriteAnimations>>,
+    library: Res<AnimationLibrary>,
+    animations: Res<Assets<SpriteAnimations>>,
+    mut q_animations: Query<(&mut SpriteAnimation, &mut Handle<Image>, &mut TextureAtlas)>,
+) {
+    let mut is_reloaded = false;
+    for event in evr_assets.read() {
+        if event.is_modified(&library.0) {
+            info!("reloaded sprite animations");
+            is_reloaded = true;
+        }
+    }
+    if !is_reloaded {
+        return;
+    }
+    let Some(animations) = animations.get(&library.0) else {
+        return;
+    };
+    for (mut animation, mut texture, mut atlas) in &mut q_animations {
+        let Some(clip) = animations.get(&animation.name) else {
+            continue;
+        };
+        // the animation may have lost frames
+        animation.position = animation.position.min(clip.frames.len() - 1);
+        *texture = clip.texture.clone();
+        atlas.layout = clip.layout.clone();
+        atlas.index = clip.frames[animation.position].index;
+    }
+}
+
+pub fn animate_sprites(
+    mut cmd: Commands,
+    time: Res<Time>,
+    library: Res<AnimationLibrary>,
+    animations: Res<Assets<SpriteAnimations>>,
+    mut evw_events: EventWriter<SpriteAnimationEvent>,
+    mut evw_finished: EventWriter<SpriteAnimationFinished>,
+    mut q_animations: Query<(Entity, &mut SpriteAnimation, &mut TextureAtlas)>,
+) {
+    let Some(animations) = animations.get(&library.0) else {
+        return;
+    };
+    for (entity, mut animation, mut atlas) in &mut q_animations {
+        if animation.is_finished {
+            continue;
+        }
+        let Some(clip) = animations.get(&animation.name) else {
+            continue;
+        };
+        animation.elapsed += time.delta_seconds() * animation.speed.max(0.);
+        // a long frame hitch can skip several frames, each of their events is still sent
+        while animation.elapsed >= clip.frames[animation.position].secs {
+            animation.elapsed -= clip.frames[animation.position].secs;
+            let Some(position) = animation.next_position(clip.frames.len(), clip.mode) else {
+                animation.is_finished = true;
+                evw_finished.send(SpriteAnimationFinished {
+                    entity,
+                    animation: animation.name.clone(),
+                });
+                if animation.despawn_on_finish {
+                    cmd.entity(entity).despawn_recursive();
+                }
+                break;
+            };
+            animation.position = position;
+            if let Some(event) = &clip.frames[position].event {
+                evw_events.send(SpriteAnimationEvent {
+                    entity,
+                    animation: animation.name.clone(),
+                    frame: position,
+                    event: event.clone(),
+                });
+            }
+        }
+        let index = clip.frames[animation.position].index;
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Positions an animation goes through, starting from the first frame
+    fn positions(n_frames: usize, mode: AnimationMode, n_steps: usize) -> Vec<Option<usize>> {
+        let mut animation = SpriteAnimation::new("test");
+        (0..n_steps)
+            .map(|_| {
+                let next = animation.next_position(n_frames, mode);
+                if let Some(position) = next {
+                    animation.position = position;
+                }
+                next
+            })
+            .collect()
+    }
+
+    fn build(text: &str) -> Result<HashMap<String, SpriteClip>, RonAssetError> {
+        let defs: HashMap<String, AnimationDef> = ron::from_str(text).unwrap();
+        let sheets = LoadedSheets::from_iter([(
+            "sheet".to_string(),
+            (Handle::default(), Handle::default(), 4),
+        )]);
+        build_clips(defs, &sheets)
+    }
+
+    #[test]
+    fn loops() {
+        let expected = [1, 2, 0, 1].map(Some);
+        assert_eq!(positions(3, AnimationMode::Loop, 4), expected);
+    }
+
+    #[test]
+    fn plays_once() {
+        let expected = [Some(1), Some(2), None, None];
+        assert_eq!(positions(3, AnimationMode::Once, 4), expected);
+    }
+
+    #[test]
+    fn ping_pongs() {
+        let expected = [1, 2, 1, 0, 1, 2].map(Some);
+        assert_eq!(positions(3, AnimationMode::PingPong, 6), expected);
+    }
+
+    #[test]
+    fn single_frame() {
+        assert_eq!(positions(1, AnimationMode::Loop, 2), [Some(0), Some(0)]);
+        assert_eq!(positions(1, AnimationMode::Once, 2), [None, None]);
+        assert_eq!(positions(1, AnimationMode::PingPong, 2), [Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn builds_clips() {
+        let clips = build(
+            r#"{
+                "spin": (
+                    sheet: "sheet",
+                    frames: [3, 1, 2],
+                    frame_secs: 0.1,
+                    durations: {1: 0.5, 2: 0.},
+                    mode: PingPong,
+                    events: {2: "hit"},
+                ),
+            }"#,
+        )
+        .unwrap();
+        let clip = &clips["spin"];
+        let indices: Vec<usize> = clip.frames.iter().map(|frame| frame.index).collect();
+        let secs: Vec<f32> = clip.frames.iter().map(|frame| frame.secs).collect();
+        assert_eq!(indices, [3, 1, 2]);
+        assert_eq!(secs, [0.1, 0.5, MIN_FRAME_SECS]);
+        assert_eq!(clip.frames[2].event.as_deref(), Some("hit"));
+        assert_eq!(clip.mode, AnimationMode::PingPong);
+    }
+
+    #[test]
+    fn rejects_unknown_sheet() {
+        let result = build(r#"{"a": (sheet: "other", frames: [0], frame_secs: 0.1)}"#);
+        assert!(matches!(
+            result,
+            Err(RonAssetError::Invalid(message)) if message == "animation a uses unknown sheet other"
+        ));
+    }
+
+    #[test]
+    fn rejects_no_frames() {
+        let result = build(r#"{"a": (sheet: "sheet", frames: [], frame_secs: 0.1)}"#);
+        assert!(matches!(
+            result,
+            Err(RonAssetError::Invalid(message)) if message == "animation a has no frames"
+        ));
+    }
+
+    #[test]
+    fn rejects_frame_out_of_sheet() {
+        let result = build(r#"{"a": (sheet: "sheet", frames: [0, 4], frame_secs: 0.1)}"#);
+        assert!(matches!(
+            result,
+            Err(RonAssetError::Invalid(message)) if message == "animation a frame 4 is outside its sheet"
+        ));
+    }
+}
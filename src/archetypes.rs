@@ -6,15 +6,16 @@ use bevy_rapier2d::{
     dynamics::{
         AdditionalMassProperties, Damping, ExternalForce, GravityScale, RigidBody, Velocity,
     },
-    geometry::{ActiveEvents, Collider, ColliderMassProperties, Restitution},
+    geometry::{ActiveEvents, Collider, ColliderMassProperties, CollisionGroups, Restitution},
 };
 
 use crate::{
     avatars::{ProjectileEmitterBundle, ThrusterBundle},
     components::{AsteroidTag, CollisionRadius, Damage, FireType, Health, ProjectileTag, TurnRate},
     game::{
-        ParticlePixelTexture, PlayerShipTexture, Speed, AMBIENT_ANGULAR_FRICTION_COEFFICIENT, AMBIENT_LINEAR_FRICTION_COEFFICIENT, BOTTOM_WALL, DEFAULT_HEALTH, DEFAULT_MOVESPEED, DEFAULT_ROTATION, DEFAULT_THRUST_FORCE_MAGNITUDE, INIT_ASTEROID_DAMAGE, INIT_ASTEROID_MOVESPEED, INIT_ASTEROID_RESTITUTION, INIT_SHIP_HEALTH, INIT_SHIP_PROJECTILE_SPEED, INIT_SHIP_TURN_RATE, LEFT_WALL, PROJECTILE_MASS, PROJECTILE_RESTITUTION, RIGHT_WALL, TOP_WALL
+        PlayerShipTexture, Speed, AMBIENT_ANGULAR_FRICTION_COEFFICIENT, AMBIENT_LINEAR_FRICTION_COEFFICIENT, BOTTOM_WALL, DEFAULT_HEALTH, DEFAULT_MOVESPEED, DEFAULT_ROTATION, DEFAULT_THRUST_FORCE_MAGNITUDE, INIT_ASTEROID_DAMAGE, INIT_ASTEROID_MOVESPEED, INIT_ASTEROID_RESTITUTION, INIT_SHIP_HEALTH, INIT_SHIP_PROJECTILE_SPEED, INIT_SHIP_TURN_RATE, LEFT_WALL, PROJECTILE_MASS, PROJECTILE_RESTITUTION, RIGHT_WALL, TOP_WALL
     },
+    physics::gameplay_collision_groups,
     utils::Heading,
 };
 
@@ -24,6 +25,7 @@ pub struct ShipBundle {
     // mesh_bundle: MaterialMesh2dBundle<M>,
     turn_rate: TurnRate,
     collider: Collider,
+    collision_groups: CollisionGroups,
     collision_events: ActiveEvents,
     health: Health,
     rigidbody: RigidBody,
@@ -42,7 +44,6 @@ impl ShipBundle {
         texture: &PlayerShipTexture,
         // mesh: Handle<Mesh>,
         // material: Handle<M>,
-        particle_pixel_texture: &ParticlePixelTexture,
     ) -> (Self, (ProjectileEmitterBundle, ThrusterBundle)) {
         (
             Self {
@@ -51,7 +52,7 @@ impl ShipBundle {
                         color: Color::rgba(1., 0., 0., 1.),
                         ..default()
                     },
-                    texture: texture.0.clone().into(),
+                    texture: texture.0.clone(),
                     transform: Transform {
                         translation: Vec3::new(x, y, 1.),
                         scale: Vec2::splat(0.8).extend(1.),
@@ -65,6 +66,7 @@ impl ShipBundle {
                     Vec2::X * 22.,
                     Vec2::new(-15., 15.),
                 ),
+                collision_groups: gameplay_collision_groups(),
                 collision_events: ActiveEvents::COLLISION_EVENTS,
                 health: Health(INIT_SHIP_HEALTH),
                 turn_rate: TurnRate(INIT_SHIP_TURN_RATE),
@@ -86,12 +88,7 @@ impl ShipBundle {
             },
             (
                 ProjectileEmitterBundle::new(22., heading, Some(FireType::Primary)),
-                ThrusterBundle::new(
-                    0.,
-                    0.,
-                    DEFAULT_THRUST_FORCE_MAGNITUDE,
-                    particle_pixel_texture.0.clone().into(),
-                ),
+                ThrusterBundle::new(0., 0., DEFAULT_THRUST_FORCE_MAGNITUDE),
             ),
         )
     }
@@ -116,15 +113,9 @@ impl ParticleBundle {
             Some(x) => x,
             None => DEFAULT_MOVESPEED,
         };
-        let heading = match heading {
-            Some(x) => x,
-            None => Heading::default(),
-        };
+        let heading = heading.unwrap_or_default();
 
-        let scale = match scale {
-            Some(x) => x,
-            None => 1.0,
-        };
+        let scale = scale.unwrap_or(1.0);
 
         Self {
             sprite: SpriteBundle {
@@ -132,7 +123,6 @@ impl ParticleBundle {
                     translation: Vec3::new(x, y, 0.),
                     rotation: heading.into(),
                     scale: Vec3::splat(scale),
-                    ..default()
                 },
                 sprite: Sprite {
                     color: color.unwrap_or_default(),
@@ -174,11 +164,14 @@ pub struct ProjectileBundle {
     damage: Damage,
     rigidbody: RigidBody,
     collider: Collider,
+    collision_groups: CollisionGroups,
     collision_events: ActiveEvents,
     velocity: Velocity,
     restitution: Restitution,
     gravity: GravityScale,
     mass: AdditionalMassProperties,
+    // which weapon fired it, decides how it looks
+    fire_type: FireType,
     tag: ProjectileTag,
 }
 
@@ -188,18 +181,16 @@ impl ProjectileBundle {
         y: f32,
         heading: Option<Heading>,
         projectile_speed: Option<Speed>,
-        color: Option<Color>,
         damage: Option<i32>,
-        restitution_coeff: Option<f32>,
-        gravity_scale: Option<f32>,
         scale: Option<f32>,
+        fire_type: Option<FireType>,
     ) -> Self {
         let projectile_speed = match projectile_speed {
             Some(x) => x,
             None => INIT_SHIP_PROJECTILE_SPEED,
         };
 
-        let particle = ParticleBundle::new(x, y, heading, Some(projectile_speed), color, scale);
+        let particle = ParticleBundle::new(x, y, heading, Some(projectile_speed), None, scale);
         let sprite = particle.sprite;
         let velocity = particle.velocity;
 
@@ -207,25 +198,18 @@ impl ProjectileBundle {
             Some(x) => Damage(x),
             None => Damage::default(),
         };
-        let restitution = match restitution_coeff {
-            Some(x) => Restitution::coefficient(x),
-            None => Restitution::coefficient(PROJECTILE_RESTITUTION),
-        };
-        let gravity = match gravity_scale {
-            Some(x) => GravityScale(x),
-            None => GravityScale(0.),
-        };
-
         Self {
             sprite,
             rigidbody: RigidBody::Dynamic,
             collider: Collider::ball(0.5),
+            collision_groups: gameplay_collision_groups(),
             collision_events: ActiveEvents::COLLISION_EVENTS,
             damage,
             velocity,
-            restitution,
-            gravity,
+            restitution: Restitution::coefficient(PROJECTILE_RESTITUTION),
+            gravity: GravityScale(0.),
             mass: AdditionalMassProperties::Mass(PROJECTILE_MASS),
+            fire_type: fire_type.unwrap_or(FireType::Primary),
             tag: ProjectileTag,
         }
     }
@@ -241,11 +225,13 @@ impl Default for ProjectileBundle {
             damage: Damage::default(),
             rigidbody: RigidBody::Dynamic,
             collider: Collider::ball(1.),
+            collision_groups: gameplay_collision_groups(),
             collision_events: ActiveEvents::COLLISION_EVENTS,
             velocity,
             restitution: Restitution::coefficient(PROJECTILE_RESTITUTION),
             gravity: GravityScale(0.),
             mass: AdditionalMassProperties::Mass(PROJECTILE_MASS),
+            fire_type: FireType::Primary,
             tag: ProjectileTag,
         }
     }
@@ -256,6 +242,7 @@ pub struct AsteroidBundle<M: Material2d> {
     mesh_bundle: MaterialMesh2dBundle<M>,
     rigidbody: RigidBody,
     collider: Collider,
+    collision_groups: CollisionGroups,
     collider_mass_properties: ColliderMassProperties,
     collision_radius: CollisionRadius,
     collision_events: ActiveEvents,
@@ -286,7 +273,6 @@ impl<M: Material2d> AsteroidBundle<M> {
         y: f32,
         velocity: Option<Velocity>,
         health: Option<i32>,
-        damage: Option<i32>,
     ) -> Self {
         let velocity = match velocity {
             Some(x) => x,
@@ -300,11 +286,6 @@ impl<M: Material2d> AsteroidBundle<M> {
             None => Health(DEFAULT_HEALTH),
         };
 
-        let damage = match damage {
-            Some(x) => Damage(x),
-            None => Damage(INIT_ASTEROID_DAMAGE),
-        };
-
         // let mut rng = rand::thread_rng();
         // let angvel = (rng.gen::<f32>() * 0.1) - 0.05;
 
@@ -316,14 +297,14 @@ impl<M: Material2d> AsteroidBundle<M> {
                     translation: Vec3::new(x, y, 2.),
                     rotation: Heading::default().into(),
                     scale: Vec2::splat(1.).extend(1.),
-                    ..default()
                 },
                 ..default()
             },
             rigidbody: RigidBody::Dynamic,
             velocity,
-            damage,
+            damage: Damage(INIT_ASTEROID_DAMAGE),
             collider: Collider::ball(r),
+            collision_groups: gameplay_collision_groups(),
             collider_mass_properties: ColliderMassProperties::Density(5.0),
             collision_radius: CollisionRadius(r),
             collision_events: ActiveEvents::COLLISION_EVENTS,
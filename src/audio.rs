@@ -1,34 +1,521 @@
-use bevy::{prelude::*, time::Stopwatch};
+use std::f32::consts::FRAC_PI_2;
 
-#[derive(Resource)]
-pub struct BackgroundMusic(pub Handle<AudioSource>);
+use bevy::{
+    audio::{SpatialScale, Volume},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_rapier2d::dynamics::{ExternalForce, Velocity};
+use rand::{seq::SliceRandom, Rng};
 
-#[derive(Resource)]
-pub struct ProjectileEmitSound(pub Handle<AudioSource>);
+use crate::{
+    components::PlayerShipTag,
+    effects::{CollisionEffectEvent, DestructionEffectEvent, FireEffectEvent, ThrustEffectEvent},
+    events::Avatars,
+    game::{MEDIUM_ASTEROID_R, SHIP_THRUST_FORCE_MAGNITUDE, SMALL_ASTEROID_R},
+    music::music_plugin,
+    settings::{AudioSettings, Settings},
+    sound_bank::{sound_bank_plugin, Sound, SoundBank, SoundBankHandle, SoundEntry},
+};
 
-#[derive(Resource)]
-pub struct ShipThrustSound(pub Handle<AudioSource>);
+// World to audio units for positional sounds. Volume falls off with the square of the distance beyond
+// 1 audio unit, so sounds within 600px of the listener play at full volume and the far side of the
+// arena at about a tenth
+pub const SPATIAL_AUDIO_SCALE: SpatialScale = SpatialScale::new_2d(1. / 600.);
+// distance between the listener's ears, in world units
+pub const LISTENER_EAR_GAP: f32 = 20.;
+// one-shots playing at once across all sounds
+pub const MAX_VOICES: usize = 24;
+// collision sounds get as loud as the hit's intensity, up to this times their sound bank volume
+pub const MAX_IMPACT_GAIN: f32 = 1.5;
+// engine loudness per second while the thrust key is held / released
+pub const ENGINE_SPOOL_UP_RATE: f32 = 6.;
+pub const ENGINE_SPOOL_DOWN_RATE: f32 = 2.5;
+// engine pitch at rest and at ENGINE_PITCH_MAX_SPEED and above
+pub const ENGINE_MIN_PITCH: f32 = 0.85;
+pub const ENGINE_MAX_PITCH: f32 = 1.3;
+pub const ENGINE_PITCH_MAX_SPEED: f32 = 600.;
 
-#[derive(Resource)]
-pub struct ProjectileImpactSound(pub Handle<AudioSource>);
+// Sounds for the effect events emitted by gameplay, the audible counterpart of effects.rs, and the music.
+// Every sound plays on a bus of the AudioMixer: one-shots go through the SoundPlayer, which picks their
+// clips from the sound bank and keeps them within their voice limits, music is in music.rs.
+// GlobalVolume is left at 1, the mixer's master gain replaces it so it can change live.
+// In-world sounds are panned and attenuated around the AudioListener, which follows the player ship.
+pub fn audio_plugin(app: &mut App) {
+    // from the settings right away so the first sounds already play at the right volume
+    let mixer = app
+        .world
+        .get_resource::<Settings>()
+        .map(|settings| AudioMixer::from(&settings.audio))
+        .unwrap_or_default();
+    app.add_plugins((sound_bank_plugin, music_plugin))
+        .insert_resource(mixer)
+        .init_resource::<SoundVoices>()
+        .add_event::<CollisionEffectEvent>()
+        .add_event::<DestructionEffectEvent>()
+        .add_event::<FireEffectEvent>()
+        .add_event::<ThrustEffectEvent>()
+        .add_systems(Startup, setup_listener)
+        .add_systems(
+            Update,
+            (
+                follow_listener,
+                apply_bus_volumes.run_if(resource_changed::<AudioMixer>),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                prune_voices,
+                (
+                    play_collision_sounds,
+                    play_destruction_sounds,
+                    play_fire_sounds,
+                    play_ui_sounds,
+                ),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                attach_engine_sounds,
+                read_thrust_events,
+                update_engine_sounds,
+            )
+                .chain(),
+        );
+}
 
-#[derive(Resource)]
-pub struct AsteroidDestroyedSound(pub Handle<AudioSource>);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Ui,
+}
 
-#[derive(Resource)]
-pub struct AsteroidClashSound(pub Handle<AudioSource>);
+// Bus gains in 0..=1, kept in sync with Settings.audio by settings::apply_settings
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AudioMixer {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub ui: f32,
+}
 
-#[derive(Resource)]
-pub struct ShipDamagedSound(pub Handle<AudioSource>);
+impl Default for AudioMixer {
+    fn default() -> Self {
+        Self::from(&AudioSettings::default())
+    }
+}
 
-#[derive(Resource)]
-pub struct SoulDestroyedSound(pub Handle<AudioSource>);
+impl From<&AudioSettings> for AudioMixer {
+    fn from(audio: &AudioSettings) -> Self {
+        Self {
+            master: audio.master.clamp(0., 1.),
+            music: audio.music.clamp(0., 1.),
+            sfx: audio.sfx.clamp(0., 1.),
+            ui: audio.ui.clamp(0., 1.),
+        }
+    }
+}
+
+impl AudioMixer {
+    // Effective gain of a bus, master included
+    pub fn gain(&self, bus: AudioBus) -> f32 {
+        let bus_gain = match bus {
+            AudioBus::Music => self.music,
+            AudioBus::Sfx => self.sfx,
+            AudioBus::Ui => self.ui,
+        };
+        self.master * bus_gain
+    }
+}
+
+// Ears for positional sounds, never rotated so left and right always match the screen
+#[derive(Component)]
+pub struct AudioListener;
+
+// Bus of a playing one-shot and its volume before the bus gain, so it follows volume changes
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BusVolume {
+    pub bus: AudioBus,
+    pub volume: f32,
+}
+
+// Plays a sound once on a bus and despawns it when done. volume is before the bus gain, speed also
+// shifts the pitch. With a position the sound is in the world, panned and attenuated relative to the
+// AudioListener
+pub fn play_one_shot(
+    cmd: &mut Commands,
+    mixer: &AudioMixer,
+    bus: AudioBus,
+    source: Handle<AudioSource>,
+    volume: f32,
+    speed: f32,
+    position: Option<Vec3>,
+) -> Entity {
+    let settings = PlaybackSettings::DESPAWN
+        .with_volume(Volume::new(volume * mixer.gain(bus)))
+        .with_speed(speed)
+        .with_spatial(position.is_some())
+        .with_spatial_scale(SPATIAL_AUDIO_SCALE);
+    let mut entity = cmd.spawn((AudioBundle { source, settings }, BusVolume { bus, volume }));
+    if let Some(position) = position {
+        entity.insert(TransformBundle::from_transform(
+            Transform::from_translation(position),
+        ));
+    }
+    entity.id()
+}
+
+// A one-shot started by the SoundPlayer
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Voice(pub Sound);
+
+struct VoiceEntry {
+    entity: Entity,
+    sound: Sound,
+    priority: u8,
+}
+
+// Voices currently playing, oldest first. Finished ones despawn themselves and are pruned every frame
+#[derive(Resource, Default)]
+pub struct SoundVoices {
+    voices: Vec<VoiceEntry>,
+    last_started: HashMap<Sound, f32>,
+}
+
+impl SoundVoices {
+    pub fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    // Frees a voice for the sound if it may start now, false if it should be dropped instead
+    fn make_room(
+        &mut self,
+        sound: Sound,
+        entry: &SoundEntry,
+        now: f32,
+        cmd: &mut Commands,
+    ) -> bool {
+        if let Some(last_started) = self.last_started.get(&sound) {
+            if now - last_started < entry.min_interval_secs {
+                return false;
+            }
+        }
+        let n_same = self
+            .voices
+            .iter()
+            .filter(|voice| voice.sound == sound)
+            .count();
+        if n_same >= entry.max_voices {
+            let oldest = self.voices.iter().position(|voice| voice.sound == sound);
+            match oldest {
+                Some(index) => self.cut_off(index, cmd),
+                None => return false,
+            }
+        } else if self.voices.len() >= MAX_VOICES {
+            // min_by_key keeps the first of equals, so the oldest of the lowest priority
+            let victim = self
+                .voices
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, voice)| voice.priority)
+                .map(|(index, voice)| (index, voice.priority));
+            match victim {
+                Some((index, priority)) if priority <= entry.priority => self.cut_off(index, cmd),
+                _ => return false,
+            }
+        }
+        self.last_started.insert(sound, now);
+        true
+    }
+
+    fn cut_off(&mut self, index: usize, cmd: &mut Commands) {
+        let voice = self.voices.remove(index);
+        // dropping the sink stops playback
+        if let Some(mut entity) = cmd.get_entity(voice.entity) {
+            entity.despawn();
+        }
+    }
+}
 
+// Plays sounds from the sound bank within their voice limits. Use this instead of spawning AudioBundles
+#[derive(SystemParam)]
+pub struct SoundPlayer<'w, 's> {
+    cmd: Commands<'w, 's>,
+    mixer: Res<'w, AudioMixer>,
+    voices: ResMut<'w, SoundVoices>,
+    bank: Res<'w, SoundBankHandle>,
+    banks: Res<'w, Assets<SoundBank>>,
+    // real time so menu sounds still respect their limits while paused
+    time: Res<'w, Time<Real>>,
+}
+
+impl SoundPlayer<'_, '_> {
+    pub fn play(&mut self, sound: Sound) -> Option<Entity> {
+        self.start(sound, None, 1.)
+    }
+
+    // Positional, panned and attenuated relative to the AudioListener
+    pub fn play_at(&mut self, sound: Sound, position: Vec3) -> Option<Entity> {
+        self.start(sound, Some(position), 1.)
+    }
+
+    // gain multiplies the sound bank's volume, e.g. louder for harder hits
+    pub fn play_with_gain(
+        &mut self,
+        sound: Sound,
+        position: Option<Vec3>,
+        gain: f32,
+    ) -> Option<Entity> {
+        self.start(sound, position, gain)
+    }
+
+    // Nothing plays while the bank is loading or if it has no clips for the sound
+    fn start(&mut self, sound: Sound, position: Option<Vec3>, gain: f32) -> Option<Entity> {
+        let entry = self.banks.get(&self.bank.0)?.get(sound)?;
+        let mut rng = rand::thread_rng();
+        let source = entry.clips.choose(&mut rng)?.clone();
+        let now = self.time.elapsed_seconds();
+        if !self.voices.make_room(sound, entry, now, &mut self.cmd) {
+            return None;
+        }
+        let volume = vary(&mut rng, entry.volume, entry.volume_variation).max(0.) * gain.max(0.);
+        let speed = vary(&mut rng, entry.pitch, entry.pitch_variation).max(0.01);
+        let entity = play_one_shot(
+            &mut self.cmd,
+            &self.mixer,
+            sound.bus(),
+            source,
+            volume,
+            speed,
+            position,
+        );
+        self.cmd.entity(entity).insert(Voice(sound));
+        self.voices.voices.push(VoiceEntry {
+            entity,
+            sound,
+            priority: entry.priority,
+        });
+        Some(entity)
+    }
+}
+
+fn vary(rng: &mut impl Rng, value: f32, variation: f32) -> f32 {
+    value + rng.gen_range(-variation..=variation)
+}
+
+pub fn prune_voices(mut voices: ResMut<SoundVoices>, q_voices: Query<(), With<Voice>>) {
+    voices
+        .voices
+        .retain(|voice| q_voices.contains(voice.entity));
+}
+
+// Looping engine layers, thrust-lo for spooling up and down and thrust-hi at full burn
 #[derive(Resource)]
-pub struct VesselDestroyedSound(pub Handle<AudioSource>);
+pub struct EngineSounds {
+    pub lo: Handle<AudioSource>,
+    pub hi: Handle<AudioSource>,
+}
+
+// Engine of a ship, its layers play as children of the ship so they go away with it
+#[derive(Component, Default, Debug)]
+pub struct EngineSound {
+    pub is_thrusting: bool,
+    // 0 idle ..= 1 full burn, eased towards the thrust actually applied
+    pub level: f32,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineLayer {
+    Lo,
+    Hi,
+}
+
+pub fn setup_listener(mut cmd: Commands) {
+    cmd.spawn((
+        SpatialListener::new(LISTENER_EAR_GAP),
+        TransformBundle::default(),
+        AudioListener,
+    ));
+}
+
+// Listen from the player ship, or from the arena center while there is none
+pub fn follow_listener(
+    q_ship: Query<&Transform, (With<PlayerShipTag>, Without<AudioListener>)>,
+    mut q_listener: Query<&mut Transform, With<AudioListener>>,
+) {
+    let position = match q_ship.get_single() {
+        Ok(ship_transform) => ship_transform.translation,
+        Err(_) => Vec3::ZERO,
+    };
+    for mut transform in &mut q_listener {
+        if transform.translation != position {
+            transform.translation = position;
+        }
+    }
+}
+
+// Volume changes reach sounds that are already playing, not only the next ones
+pub fn apply_bus_volumes(
+    mixer: Res<AudioMixer>,
+    q_sounds: Query<(&BusVolume, &AudioSink)>,
+    q_spatial_sounds: Query<(&BusVolume, &SpatialAudioSink)>,
+) {
+    for (bus_volume, sink) in &q_sounds {
+        sink.set_volume(bus_volume.volume * mixer.gain(bus_volume.bus));
+    }
+    for (bus_volume, sink) in &q_spatial_sounds {
+        sink.set_volume(bus_volume.volume * mixer.gain(bus_volume.bus));
+    }
+}
+
+pub fn play_collision_sounds(
+    mut evr_coll_effects: EventReader<CollisionEffectEvent>,
+    mut sounds: SoundPlayer,
+) {
+    for event in evr_coll_effects.read() {
+        let sound = match (event.avatar_a, event.avatar_b) {
+            (Avatars::Projectile, Some(Avatars::Asteroid)) => Sound::ProjectileHitAsteroid,
+            (Avatars::Projectile, _) => Sound::ProjectileHit,
+            (Avatars::PlayerShip, _) => Sound::ShipDamaged,
+            (Avatars::Asteroid, Some(Avatars::Asteroid)) => {
+                match event.collision_radius_a.map(|r| r.0) {
+                    Some(r) if r <= SMALL_ASTEROID_R => Sound::AsteroidClashSmall,
+                    Some(r) if r <= MEDIUM_ASTEROID_R => Sound::AsteroidClashMedium,
+                    _ => Sound::AsteroidClashLarge,
+                }
+            }
+            _ => continue,
+        };
+        let gain = event.intensity().min(MAX_IMPACT_GAIN);
+        sounds.play_with_gain(sound, event.position(), gain);
+    }
+}
+
+pub fn play_destruction_sounds(
+    mut evr_destruction_effects: EventReader<DestructionEffectEvent>,
+    mut sounds: SoundPlayer,
+) {
+    for event in evr_destruction_effects.read() {
+        match event.avatar {
+            // the ship is the listener, its own end is heard centered
+            Avatars::PlayerShip => {
+                // Post v1.0: astral/cosmic/etheral body gameplay
+                // sounds.play(Sound::SoulDestroyed);
+                sounds.play(Sound::ShipDestroyed);
+                sounds.play(Sound::ShipDamaged);
+            }
+            Avatars::Asteroid => {
+                sounds.play_at(Sound::AsteroidDestroyed, event.transform.translation);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn play_fire_sounds(
+    mut evr_fire_effects: EventReader<FireEffectEvent>,
+    mut sounds: SoundPlayer,
+) {
+    for _ in evr_fire_effects.read() {
+        sounds.play(Sound::ProjectileFired);
+    }
+}
+
+pub fn attach_engine_sounds(
+    mut cmd: Commands,
+    q_ships: Query<Entity, Added<PlayerShipTag>>,
+    engine_sounds: Res<EngineSounds>,
+) {
+    for ship in &q_ships {
+        cmd.entity(ship)
+            .insert(EngineSound::default())
+            .with_children(|parent| {
+                for (layer, source) in [
+                    (EngineLayer::Lo, engine_sounds.lo.clone()),
+                    (EngineLayer::Hi, engine_sounds.hi.clone()),
+                ] {
+                    parent.spawn((
+                        AudioBundle {
+                            source,
+                            settings: PlaybackSettings::LOOP.with_volume(Volume::ZERO),
+                        },
+                        layer,
+                    ));
+                }
+            });
+    }
+}
+
+pub fn read_thrust_events(
+    mut evr_thrust_effect: EventReader<ThrustEffectEvent>,
+    mut q_engines: Query<&mut EngineSound>,
+) {
+    for event in evr_thrust_effect.read() {
+        if let Ok(mut engine) = q_engines.get_mut(event.id) {
+            engine.is_thrusting = event.is_thrusting;
+        }
+    }
+}
+
+// Volume follows the applied thrust, fading in and out with the thrust key, and crossfades from the lo
+// to the hi layer as it rises. Pitch follows the ship's speed. Paused while the game is
+pub fn update_engine_sounds(
+    time: Res<Time<Virtual>>,
+    mixer: Res<AudioMixer>,
+    mut q_ships: Query<(&mut EngineSound, &Children, &ExternalForce, &Velocity)>,
+    q_layers: Query<(&EngineLayer, &AudioSink)>,
+) {
+    for (mut engine, children, force, velocity) in &mut q_ships {
+        let target = match engine.is_thrusting {
+            true => (force.force.length() / SHIP_THRUST_FORCE_MAGNITUDE).clamp(0., 1.),
+            false => 0.,
+        };
+        let rate = match target > engine.level {
+            true => ENGINE_SPOOL_UP_RATE,
+            false => ENGINE_SPOOL_DOWN_RATE,
+        };
+        let step = rate * time.delta_seconds();
+        engine.level += (target - engine.level).clamp(-step, step);
+
+        // equal power crossfade so the engine doesn't dip in loudness between the layers
+        let angle = engine.level * FRAC_PI_2;
+        let gain = engine.level * mixer.gain(AudioBus::Sfx);
+        let speed = (velocity.linvel.length() / ENGINE_PITCH_MAX_SPEED).clamp(0., 1.);
+        let pitch = ENGINE_MIN_PITCH + (ENGINE_MAX_PITCH - ENGINE_MIN_PITCH) * speed;
 
-// #[derive(Resource)]
-// pub struct ShipImpactSound(Handle<AudioSource>);
+        for (layer, sink) in q_layers.iter_many(children) {
+            let volume = match layer {
+                EngineLayer::Lo => gain * angle.cos(),
+                EngineLayer::Hi => gain * angle.sin(),
+            };
+            sink.set_volume(volume);
+            sink.set_speed(pitch);
+            match time.is_paused() {
+                true => sink.pause(),
+                false => sink.play(),
+            }
+        }
+    }
+}
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct ShipThrustSoundStopwatch(pub Stopwatch);
+// Click for every button press in the menus, sliders included
+pub fn play_ui_sounds(
+    q_buttons: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    mut sounds: SoundPlayer,
+) {
+    for interaction in &q_buttons {
+        if *interaction == Interaction::Pressed {
+            sounds.play(Sound::UiClick);
+        }
+    }
+}
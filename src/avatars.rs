@@ -1,25 +1,22 @@
-use std::f32::consts::PI;
-
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
-use bevy_particle_systems::{
-    CircleSegment, ColorOverTime, Curve, CurvePoint, JitteredValue, ParticleSystem,
-    ParticleSystemBundle, ParticleTexture,
-};
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 
 use crate::{
     archetypes::{AsteroidBundle, AsteroidSizes},
     components::{
         FireType, Health, PlayerShipTag, PrimaryThrustMagnitude, ProjectileEmission, TurnRate,
     },
+    effect_library::PersistentEffect,
     game::{
-        OnPlayScreen, ParticlePixelTexture, PlayerShipTexture, Speed,
-        AMBIENT_ANGULAR_FRICTION_COEFFICIENT, AMBIENT_LINEAR_FRICTION_COEFFICIENT,
-        DEFAULT_MOVESPEED, DEFAULT_THRUST_FORCE_MAGNITUDE, INIT_SHIP_HEALTH, INIT_SHIP_RESTITUTION,
-        INIT_SHIP_TURN_RATE, LARGE_ASTEROID_HEALTH, LARGE_ASTEROID_R, MEDIUM_ASTEROID_HEALTH,
-        MEDIUM_ASTEROID_R, SHIP_HALF_WIDTH, SHIP_LENGTH_AFT, SHIP_LENGTH_FORE,
-        SHIP_THRUST_FORCE_MAGNITUDE, SMALL_ASTEROID_HEALTH, SMALL_ASTEROID_R,
+        OnPlayScreen, PlayerShipTexture, AMBIENT_ANGULAR_FRICTION_COEFFICIENT,
+        AMBIENT_LINEAR_FRICTION_COEFFICIENT, DEFAULT_THRUST_FORCE_MAGNITUDE, INIT_SHIP_HEALTH,
+        INIT_SHIP_RESTITUTION, INIT_SHIP_TURN_RATE, LARGE_ASTEROID_HEALTH, LARGE_ASTEROID_R,
+        MEDIUM_ASTEROID_HEALTH, MEDIUM_ASTEROID_R, SHIP_HALF_WIDTH, SHIP_LENGTH_AFT,
+        SHIP_LENGTH_FORE, SHIP_THRUST_FORCE_MAGNITUDE, SMALL_ASTEROID_HEALTH, SMALL_ASTEROID_R,
     },
+    physics::gameplay_collision_groups,
+    rng::GameRng,
     utils::Heading,
 };
 
@@ -34,6 +31,7 @@ pub struct PlayerShip {
     sprite_bundle: SpriteBundle,
     turn_rate: TurnRate,
     collider: Collider,
+    collision_groups: CollisionGroups,
     collision_events: ActiveEvents,
     health: Health,
     rigidbody: RigidBody,
@@ -51,7 +49,6 @@ impl PlayerShip {
         y: f32,
         heading: Option<Heading>,
         texture: &PlayerShipTexture,
-        particle_pixel_texture: &ParticlePixelTexture,
     ) -> (PlayerShip, (ProjectileEmitterBundle, ThrusterBundle)) {
         (
             PlayerShip {
@@ -75,6 +72,7 @@ impl PlayerShip {
                     Vec2::Y * SHIP_LENGTH_FORE,
                     Vec2::new(SHIP_HALF_WIDTH, -SHIP_LENGTH_AFT),
                 ),
+                collision_groups: gameplay_collision_groups(),
                 collision_events: ActiveEvents::COLLISION_EVENTS,
                 health: Health(INIT_SHIP_HEALTH),
                 turn_rate: TurnRate(INIT_SHIP_TURN_RATE),
@@ -99,12 +97,7 @@ impl PlayerShip {
             },
             (
                 ProjectileEmitterBundle::new(22., heading, Some(FireType::Primary)), // this is ship fire
-                ThrusterBundle::new(
-                    0.,
-                    0.,
-                    SHIP_THRUST_FORCE_MAGNITUDE,
-                    particle_pixel_texture.0.clone().into(),
-                ),
+                ThrusterBundle::new(0., 0., SHIP_THRUST_FORCE_MAGNITUDE),
             ),
         )
     }
@@ -114,10 +107,9 @@ impl PlayerShip {
         y: f32,
         heading: Option<Heading>,
         texture: &PlayerShipTexture,
-        particle_pixel_texture: &ParticlePixelTexture,
         cmd: &mut Commands,
     ) {
-        let (ship, children) = PlayerShip::new(x, y, heading, texture, particle_pixel_texture);
+        let (ship, children) = PlayerShip::new(x, y, heading, texture);
         cmd
             .spawn(ship)
             .with_children(|parent| {
@@ -196,43 +188,21 @@ impl Default for Thrust {
 #[derive(Bundle)]
 pub struct ThrusterBundle {
     thrust: Thrust,
-    particles: ParticleSystemBundle,
+    // particles are attached by the effects side, see effect_library.rs
+    effect: PersistentEffect,
+    transform: TransformBundle,
 }
 
 impl ThrusterBundle {
-    pub fn new(_x: f32, _y: f32, thrust: f32, particle_texture: ParticleTexture) -> ThrusterBundle {
+    pub fn new(_x: f32, _y: f32, thrust: f32) -> ThrusterBundle {
         ThrusterBundle {
             thrust: Thrust(thrust),
-            particles: ParticleSystemBundle {
-                particle_system: ParticleSystem {
-                    max_particles: 1000,
-                    texture: particle_texture,
-                    spawn_rate_per_second: 100.0.into(),
-                    initial_speed: JitteredValue::jittered(200.0, -50.0..0.0),
-                    lifetime: JitteredValue::jittered(2.0, -1.0..1.0),
-                    color: ColorOverTime::Gradient(Curve::new(vec![
-                        CurvePoint::new(Color::VIOLET, 0.0),
-                        CurvePoint::new(Color::RED, 0.1),
-                        CurvePoint::new(Color::RED, 1.0),
-                    ])),
-                    emitter_shape: CircleSegment {
-                        radius: 30.0.into(),
-                        opening_angle: std::f32::consts::PI / 12.,
-                        // direction_angle: Heading::default().to_radians() + PI,
-                        direction_angle: -PI / 2.0,
-                    }
-                    .into(),
-                    looping: true,
-                    rotate_to_movement_direction: true,
-                    initial_rotation: (0.0_f32).to_radians().into(),
-                    system_duration_seconds: 10.0,
-                    max_distance: Some(100.0),
-                    scale: 1.0.into(),
-                    ..ParticleSystem::default()
-                },
-                transform: Transform::from_xyz(0., SHIP_LENGTH_AFT, 0.0),
-                ..ParticleSystemBundle::default()
-            },
+            effect: PersistentEffect::new("thruster"),
+            transform: TransformBundle::from_transform(Transform::from_xyz(
+                0.,
+                SHIP_LENGTH_AFT,
+                0.0,
+            )),
         }
     }
 }
@@ -254,28 +224,22 @@ pub struct Asteroid {
 }
 
 impl Asteroid {
-    pub fn new(
+    pub fn bundle(
         size: AsteroidSizes,
         n_sides: usize,
-        x: f32,
-        y: f32,
-        heading: Option<Heading>,
-        speed: Option<Speed>,
-        mesh_handles: Vec<Handle<Mesh>>,
-        material_handles: Vec<Handle<ColorMaterial>>,
+        position: Vec2,
+        linvel: Vec2,
+        mesh_handles: &[Handle<Mesh>],
+        material_handles: &[Handle<ColorMaterial>],
+        rng: &mut GameRng,
     ) -> AsteroidBundle<ColorMaterial> {
         let r = match size {
             AsteroidSizes::Small => SMALL_ASTEROID_R,
             AsteroidSizes::Medium => MEDIUM_ASTEROID_R,
             AsteroidSizes::Large => LARGE_ASTEROID_R,
         };
-        let heading = heading.unwrap_or_default();
-        let speed = speed.unwrap_or(DEFAULT_MOVESPEED);
-        let angvel = rand::random::<f32>() * 0.5 - 0.25;
-        let velocity = Velocity {
-            linvel: heading.linvel(speed),
-            angvel,
-        };
+        let angvel = rng.gen::<f32>() * 0.5 - 0.25;
+        let velocity = Velocity { linvel, angvel };
         let (handle_mesh, health) = match r {
             SMALL_ASTEROID_R => (
                 match n_sides {
@@ -310,37 +274,12 @@ impl Asteroid {
             handle_mesh,
             material_handles[0].clone(),
             r,
-            x,
-            y,
+            position.x,
+            position.y,
             Some(velocity),
             Some(health),
-            None,
         )
     }
-    pub fn spawn(
-        size: AsteroidSizes,
-        n_sides: usize,
-        x: f32,
-        y: f32,
-        heading: Option<Heading>,
-        speed: Option<Speed>,
-        mesh_handles: Vec<Handle<Mesh>>,
-        material_handles: Vec<Handle<ColorMaterial>>,
-        cmd: &mut Commands,
-    ) {
-        cmd
-            .spawn(Asteroid::new(
-                size,
-                n_sides,
-                x,
-                y,
-                heading,
-                speed,
-                mesh_handles,
-                material_handles,
-            ))
-            .insert(OnPlayScreen);
-    }
 }
 
 // can be used for any avatar that has a mesh and material
@@ -382,7 +321,6 @@ pub fn gen_projectile(
     y: f32,
     heading: Option<Heading>,
     texture: &PlayerShipTexture,
-    particle_pixel_texture: &ParticlePixelTexture,
 ) -> (PlayerShip, (ProjectileEmitterBundle, ThrusterBundle)) {
     (
         PlayerShip {
@@ -406,6 +344,7 @@ pub fn gen_projectile(
                 Vec2::Y * SHIP_LENGTH_FORE,
                 Vec2::new(SHIP_HALF_WIDTH, -SHIP_LENGTH_AFT),
             ),
+            collision_groups: gameplay_collision_groups(),
             collision_events: ActiveEvents::COLLISION_EVENTS,
             health: Health(INIT_SHIP_HEALTH),
             turn_rate: TurnRate(INIT_SHIP_TURN_RATE),
@@ -430,12 +369,7 @@ pub fn gen_projectile(
         },
         (
             ProjectileEmitterBundle::new(22., heading, Some(FireType::Primary)), // this is ship fire
-            ThrusterBundle::new(
-                0.,
-                0.,
-                SHIP_THRUST_FORCE_MAGNITUDE,
-                particle_pixel_texture.0.clone().into(),
-            ),
+            ThrusterBundle::new(0., 0., SHIP_THRUST_FORCE_MAGNITUDE),
         ),
     )
 }
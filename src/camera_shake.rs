@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+
+use crate::{
+    display::MainCamera,
+    effects::{CollisionEffectEvent, DestructionEffectEvent},
+    events::Avatars,
+    game::MEDIUM_ASTEROID_R,
+};
+
+// Trauma based camera shake. Hits and explosions add trauma (0..=1) that decays over time, the camera
+// is offset and rolled by trauma squared, so small hits barely register while big ones stack up.
+// Offsets follow Perlin noise instead of jumping randomly so the camera sways rather than jitters.
+// Purely visual: its noise is not the game's rng and nothing in the simulation reads the camera.
+// Switched off by the screen shake accessibility setting.
+
+// pixels and radians at full trauma
+pub const MAX_SHAKE_OFFSET: f32 = 14.;
+pub const MAX_SHAKE_ROLL: f32 = 0.03;
+// noise samples per second, how fast the camera sways
+pub const SHAKE_FREQUENCY: f32 = 18.;
+pub const TRAUMA_DECAY_PER_SEC: f32 = 1.2;
+
+// Trauma added per hit of typical intensity, or per destruction
+pub const PROJECTILE_HIT_TRAUMA: f32 = 0.04;
+pub const ASTEROID_CLASH_TRAUMA: f32 = 0.06;
+pub const SHIP_HIT_TRAUMA: f32 = 0.3;
+// for a medium asteroid, scales with its radius
+pub const ASTEROID_DESTROYED_TRAUMA: f32 = 0.15;
+pub const SHIP_DESTROYED_TRAUMA: f32 = 0.8;
+
+const SHAKE_SEED: u32 = 0x5A4E;
+
+pub fn camera_shake_plugin(app: &mut App) {
+    app.init_resource::<CameraShake>().add_systems(
+        Update,
+        (add_collision_trauma, add_destruction_trauma, shake_camera).chain(),
+    );
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CameraShake {
+    pub trauma: f32,
+    // from the screen shake setting
+    pub enabled: bool,
+    noise: Perlin,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.,
+            enabled: true,
+            noise: Perlin::new(SHAKE_SEED),
+        }
+    }
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        if self.enabled {
+            self.trauma = (self.trauma + trauma).clamp(0., 1.);
+        }
+    }
+
+    // Offset and roll at time t (seconds), each on its own noise channel
+    fn offset(&self, t: f32) -> (Vec2, f32) {
+        let shake = self.trauma * self.trauma;
+        let sample = |channel: f64| self.noise.get([(t * SHAKE_FREQUENCY) as f64, channel]) as f32;
+        let offset = Vec2::new(sample(0.5), sample(10.5)) * MAX_SHAKE_OFFSET * shake;
+        (offset, sample(20.5) * MAX_SHAKE_ROLL * shake)
+    }
+}
+
+pub fn add_collision_trauma(
+    mut evr_coll_effects: EventReader<CollisionEffectEvent>,
+    mut shake: ResMut<CameraShake>,
+) {
+    for event in evr_coll_effects.read() {
+        let trauma = match (event.avatar_a, event.avatar_b) {
+            (Avatars::Projectile, _) => PROJECTILE_HIT_TRAUMA,
+            (Avatars::PlayerShip, _) => SHIP_HIT_TRAUMA,
+            (Avatars::Asteroid, Some(Avatars::Asteroid)) => ASTEROID_CLASH_TRAUMA,
+            _ => continue,
+        };
+        shake.add_trauma(trauma * event.intensity());
+    }
+}
+
+pub fn add_destruction_trauma(
+    mut evr_destructions: EventReader<DestructionEffectEvent>,
+    mut shake: ResMut<CameraShake>,
+) {
+    for event in evr_destructions.read() {
+        let trauma = match event.avatar {
+            Avatars::PlayerShip => SHIP_DESTROYED_TRAUMA,
+            Avatars::Asteroid => {
+                let radius = event.collision_radius.map_or(MEDIUM_ASTEROID_R, |r| r.0);
+                ASTEROID_DESTROYED_TRAUMA * radius / MEDIUM_ASTEROID_R
+            }
+            _ => continue,
+        };
+        shake.add_trauma(trauma);
+    }
+}
+
+// The camera rests at the origin, so the shake is written over its position rather than added to it
+pub fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<CameraShake>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !shake.enabled {
+        shake.trauma = 0.;
+    }
+    let (offset, roll) = shake.offset(time.elapsed_seconds());
+    for mut transform in &mut q_camera {
+        let translation = offset.extend(transform.translation.z);
+        let rotation = Quat::from_rotation_z(roll);
+        if transform.translation != translation || transform.rotation != rotation {
+            transform.translation = translation;
+            transform.rotation = rotation;
+        }
+    }
+    if shake.trauma > 0. {
+        shake.trauma = (shake.trauma - TRAUMA_DECAY_PER_SEC * time.delta_seconds()).max(0.);
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::WindowMode};
+use clap::Parser;
+
+use crate::{
+    game::{GameState, DEFAULT_RNG_SEED},
+    play::PlayLayout,
+    settings::{GameplaySettings, VideoSettings},
+};
+
+// Ticks simulated by --headless when neither --steps nor --replay say otherwise, one minute at 64Hz
+pub const DEFAULT_HEADLESS_STEPS: u32 = 3840;
+
+#[derive(Parser, Resource, Clone, Debug)]
+#[command(version, about = "Aion, an asteroids-like in space")]
+pub struct LaunchArgs {
+    /// Window width in logical pixels, defaults to the last used size
+    #[arg(long)]
+    pub width: Option<f32>,
+
+    /// Window height in logical pixels, defaults to the last used size
+    #[arg(long)]
+    pub height: Option<f32>,
+
+    /// Start in borderless fullscreen
+    #[arg(long, conflicts_with = "windowed")]
+    pub fullscreen: bool,
+
+    /// Start windowed even if fullscreen was last used
+    #[arg(long)]
+    pub windowed: bool,
+
+    /// Index of the monitor to open the window on, defaults to the primary monitor
+    #[arg(long)]
+    pub monitor: Option<usize>,
+
+    /// Game state to start in
+    #[arg(long, value_enum, default_value_t = GameState::Menu)]
+    pub state: GameState,
+
+    /// Seed for asteroid layouts and everything else random in gameplay
+    #[arg(long, default_value_t = DEFAULT_RNG_SEED)]
+    pub seed: u64,
+
+    /// Run the simulation without window, renderer or audio and print the outcome
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Fixed ticks to simulate with --headless, defaults to the length of --replay
+    #[arg(long, requires = "headless")]
+    pub steps: Option<u32>,
+
+    /// Replay file to play back instead of reading the keyboard
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+
+    /// Record the run to this replay file
+    #[arg(long, conflicts_with_all = ["replay", "headless"])]
+    pub record: Option<PathBuf>,
+
+    /// Settings file to use instead of the one in the platform config directory, --headless uses no settings
+    #[arg(long, conflicts_with = "headless")]
+    pub config: Option<PathBuf>,
+
+    /// Play the hand placed dev asteroid layout instead of a random asteroid field
+    #[arg(long)]
+    pub sandbox: bool,
+}
+
+impl LaunchArgs {
+    // Window from the saved video settings, overridden by whatever was given on the command line
+    pub fn window(&self, video: &VideoSettings) -> Window {
+        let mode = match (self.fullscreen, self.windowed) {
+            (true, _) => WindowMode::BorderlessFullscreen,
+            (_, true) => WindowMode::Windowed,
+            _ => video.mode,
+        };
+        let position = match (self.monitor.or(video.monitor), video.position) {
+            (Some(index), _) => WindowPosition::Centered(MonitorSelection::Index(index)),
+            (None, Some(position)) => WindowPosition::At(position),
+            (None, None) => WindowPosition::Centered(MonitorSelection::Primary),
+        };
+        Window {
+            title: "Aion v0.1.0".to_string(),
+            position,
+            resolution: (
+                self.width.unwrap_or(video.width),
+                self.height.unwrap_or(video.height),
+            )
+                .into(),
+            mode,
+            ..default()
+        }
+    }
+
+    pub fn layout(&self, gameplay: &GameplaySettings) -> PlayLayout {
+        match self.sandbox || gameplay.sandbox {
+            true => PlayLayout::Sandbox,
+            false => PlayLayout::Field,
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+// Popup text in the world: damage dealt, points scored and the like. Gameplay sends CombatTextEvent
+// from where damage and score happen, see physics.rs, and texts drift up from there and fade out.
+// The text entities are pooled: expired ones are hidden and reused instead of despawned, and once
+// MAX_COMBAT_TEXTS are showing, the oldest is taken over.
+
+pub const MAX_COMBAT_TEXTS: usize = 32;
+pub const COMBAT_TEXT_SECS: f32 = 0.9;
+// pixels per second, slowing to a stop as the text fades
+pub const COMBAT_TEXT_RISE_SPEED: f32 = 60.;
+// sideways offset between texts sent in the same frame, so they don't stack exactly
+pub const COMBAT_TEXT_SPREAD: f32 = 10.;
+pub const COMBAT_TEXT_Z: f32 = 50.;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombatText {
+    Damage(i32),
+    Points(usize),
+}
+
+impl CombatText {
+    fn label(&self) -> String {
+        match self {
+            CombatText::Damage(damage) => format!("-{}", damage),
+            CombatText::Points(points) => format!("+{}", points),
+        }
+    }
+
+    fn style(&self) -> (f32, Color) {
+        match self {
+            CombatText::Damage(_) => (18., Color::rgb(1., 0.35, 0.3)),
+            CombatText::Points(_) => (20., Color::rgb(1., 0.85, 0.3)),
+        }
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct CombatTextEvent {
+    pub position: Vec3,
+    pub text: CombatText,
+}
+
+pub fn combat_text_plugin(app: &mut App) {
+    app.add_event::<CombatTextEvent>()
+        .init_resource::<CombatTextPool>()
+        .add_systems(Update, (spawn_combat_texts, animate_combat_texts).chain());
+}
+
+// Every text entity ever spawned, showing or not
+#[derive(Resource, Default)]
+pub struct CombatTextPool {
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct FloatingText {
+    pub age: Timer,
+    pub origin: Vec3,
+    pub color: Color,
+}
+
+impl FloatingText {
+    fn new(origin: Vec3, color: Color) -> Self {
+        Self {
+            age: Timer::from_seconds(COMBAT_TEXT_SECS, TimerMode::Once),
+            origin,
+            color,
+        }
+    }
+}
+
+pub fn spawn_combat_texts(
+    mut cmd: Commands,
+    mut evr_texts: EventReader<CombatTextEvent>,
+    mut pool: ResMut<CombatTextPool>,
+    mut q_texts: Query<(
+        &mut FloatingText,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for (n, event) in evr_texts.read().enumerate() {
+        let spread = ((n % 3) as f32 - 1.) * COMBAT_TEXT_SPREAD;
+        let origin = event.position.truncate().extend(COMBAT_TEXT_Z) + Vec3::X * spread;
+        let (font_size, color) = event.text.style();
+        let section = TextSection::new(
+            event.text.label(),
+            TextStyle {
+                font_size,
+                color,
+                ..default()
+            },
+        );
+
+        // the oldest text, if it's hidden or there's no room for another one. Texts spawned this frame
+        // aren't in the query yet, they're the newest anyway
+        let oldest = pool
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let (floating, ..) = q_texts.get(*entity).ok()?;
+                Some((
+                    *entity,
+                    floating.age.elapsed_secs(),
+                    floating.age.finished(),
+                ))
+            })
+            .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+        let is_full = pool.entities.len() >= MAX_COMBAT_TEXTS;
+        let reused = oldest
+            .filter(|(_, _, is_hidden)| is_full || *is_hidden)
+            .map(|(entity, ..)| entity);
+        match reused.and_then(|entity| q_texts.get_mut(entity).ok()) {
+            Some((mut floating, mut text, mut transform, mut visibility)) => {
+                *floating = FloatingText::new(origin, color);
+                text.sections = vec![section];
+                transform.translation = origin;
+                *visibility = Visibility::Visible;
+            }
+            // every text in the pool was sent this frame, this one is dropped
+            None if is_full => {}
+            None => {
+                let entity = cmd
+                    .spawn((
+                        Text2dBundle {
+                            text: Text::from_sections([section]),
+                            transform: Transform::from_translation(origin),
+                            ..default()
+                        },
+                        FloatingText::new(origin, color),
+                    ))
+                    .id();
+                pool.entities.push(entity);
+            }
+        }
+    }
+}
+
+// Rise and fade, then hide until reused
+pub fn animate_combat_texts(
+    time: Res<Time>,
+    mut q_texts: Query<(
+        &mut FloatingText,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for (mut floating, mut text, mut transform, mut visibility) in &mut q_texts {
+        if floating.age.finished() {
+            continue;
+        }
+        floating.age.tick(time.delta());
+        if floating.age.finished() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        let t = floating.age.fraction();
+        // eases out, fastest when it appears
+        let rise = COMBAT_TEXT_RISE_SPEED * COMBAT_TEXT_SECS * (t - t * t / 2.);
+        transform.translation = floating.origin + Vec3::Y * rise;
+        let color = floating.color.with_a(floating.color.a() * (1. - t * t));
+        for section in &mut text.sections {
+            section.style.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_never_grows_past_max() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(combat_text_plugin);
+        for frame in 0..2 {
+            for n in 0..MAX_COMBAT_TEXTS + 8 {
+                app.world.send_event(CombatTextEvent {
+                    position: Vec3::new(n as f32, frame as f32, 0.),
+                    text: CombatText::Damage(1),
+                });
+            }
+            app.update();
+            assert_eq!(
+                app.world.resource::<CombatTextPool>().entities.len(),
+                MAX_COMBAT_TEXTS
+            );
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use bevy::utils::{Duration, Instant};
+use bevy::utils::Duration;
 
 use crate::game::{
     Speed, TurnSpeed, DEFAULT_DAMAGE, DEFAULT_DURATION_SECS, DEFAULT_HEALTH,
@@ -55,7 +55,7 @@ impl Default for Damage {
 #[derive(Component)]
 pub struct ScoreboardUi;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum FireType {
     // #[default]
     Primary,
@@ -83,7 +83,8 @@ pub struct ProjectileEmission {
     pub projectile_duration: Duration,
     pub damage: i32,
     pub is_friendly: bool,
-    pub last_emission_time: Instant,
+    // elapsed Time<Fixed> at the last emission, None if never fired
+    pub last_emission_time: Option<Duration>,
 }
 
 impl ProjectileEmission {
@@ -99,7 +100,7 @@ impl ProjectileEmission {
             projectile_duration,
             damage,
             is_friendly: false,
-            last_emission_time: Instant::now(),
+            last_emission_time: None,
         }
     }
 }
@@ -112,7 +113,7 @@ impl Default for ProjectileEmission {
             projectile_duration: Duration::from_secs(DEFAULT_DURATION_SECS),
             damage: DEFAULT_DAMAGE,
             is_friendly: false,
-            last_emission_time: Instant::now(),
+            last_emission_time: None,
         }
     }
 }
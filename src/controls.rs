@@ -1,22 +1,153 @@
-use std::time::{Duration, Instant};
-
 use bevy::prelude::*;
-use bevy_rapier2d::dynamics::ExternalForce;
+use bevy_rapier2d::dynamics::{Damping, ExternalForce};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     archetypes::ProjectileBundle,
-    audio::{ProjectileEmitSound, ShipThrustSound, ShipThrustSoundStopwatch},
     avatars::Thrust,
     components::{FireType, PlayerShipTag, ProjectileEmission, TurnRate},
-    effects::ThrustEffectEvent,
-    game::OnPlayScreen,
+    effects::{FireEffectEvent, ThrustEffectEvent},
+    game::{OnPlayScreen, AMBIENT_LINEAR_FRICTION_COEFFICIENT},
     utils::Heading,
 };
 
-pub fn ship_turn(
+// Gameplay never reads the keyboard directly. Each fixed tick the pressed state of every action is sampled
+// into ShipInput (from the keyboard, or from a replay during playback), which keeps inputs recordable and
+// the simulation deterministic.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShipAction {
+    TurnLeft,
+    TurnRight,
+    Thrust,
+    Fire,
+    Restart,
+}
+
+impl ShipAction {
+    pub const ALL: [ShipAction; 5] = [
+        ShipAction::TurnLeft,
+        ShipAction::TurnRight,
+        ShipAction::Thrust,
+        ShipAction::Fire,
+        ShipAction::Restart,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// Keyboard key for each action, persisted with the user settings
+#[derive(Resource, Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub turn_left: KeyCode,
+    pub turn_right: KeyCode,
+    pub thrust: KeyCode,
+    pub fire: KeyCode,
+    pub restart: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            turn_left: KeyCode::KeyA,
+            turn_right: KeyCode::KeyD,
+            thrust: KeyCode::KeyS,
+            fire: KeyCode::Space,
+            restart: KeyCode::KeyR,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: ShipAction) -> KeyCode {
+        match action {
+            ShipAction::TurnLeft => self.turn_left,
+            ShipAction::TurnRight => self.turn_right,
+            ShipAction::Thrust => self.thrust,
+            ShipAction::Fire => self.fire,
+            ShipAction::Restart => self.restart,
+        }
+    }
+
+    pub fn key_mut(&mut self, action: ShipAction) -> &mut KeyCode {
+        match action {
+            ShipAction::TurnLeft => &mut self.turn_left,
+            ShipAction::TurnRight => &mut self.turn_right,
+            ShipAction::Thrust => &mut self.thrust,
+            ShipAction::Fire => &mut self.fire,
+            ShipAction::Restart => &mut self.restart,
+        }
+    }
+}
+
+// Pressed state of all actions for a single tick, one bit per ShipAction
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Deref, DerefMut)]
+pub struct ActionBits(pub u8);
+
+impl ActionBits {
+    pub fn contains(&self, action: ShipAction) -> bool {
+        self.0 & action.bit() != 0
+    }
+
+    pub fn set(&mut self, action: ShipAction, is_pressed: bool) {
+        if is_pressed {
+            self.0 |= action.bit();
+        } else {
+            self.0 &= !action.bit();
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ShipInput {
+    pub current: ActionBits,
+    pub previous: ActionBits,
+}
+
+impl ShipInput {
+    pub fn advance(&mut self, next: ActionBits) {
+        self.previous = self.current;
+        self.current = next;
+    }
+
+    pub fn pressed(&self, action: ShipAction) -> bool {
+        self.current.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: ShipAction) -> bool {
+        self.current.contains(action) && !self.previous.contains(action)
+    }
+
+    pub fn just_released(&self, action: ShipAction) -> bool {
+        !self.current.contains(action) && self.previous.contains(action)
+    }
+}
+
+pub fn sample_keyboard_input(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut ship_input: ResMut<ShipInput>,
+) {
+    let mut bits = ActionBits::default();
+    for action in ShipAction::ALL {
+        bits.set(action, keyboard_input.pressed(key_bindings.key(action)));
+    }
+    ship_input.advance(bits);
+}
+
+pub fn is_restart_requested(ship_input: Res<ShipInput>) -> bool {
+    ship_input.just_pressed(ShipAction::Restart)
+}
+
+pub fn ship_turn(
+    ship_input: Res<ShipInput>,
     mut query: Query<(&mut Transform, &TurnRate), With<PlayerShipTag>>,
-    time: Res<Time>,
+    time: Res<Time<Fixed>>,
 ) {
     for (mut transform, turnrate) in query.iter_mut() {
         // let mut thrust = 0.;
@@ -30,10 +161,10 @@ pub fn ship_turn(
         // transform.translation += translation_delta;
 
         let mut rotation_sign = 0.;
-        if keyboard_input.pressed(KeyCode::KeyA) {
+        if ship_input.pressed(ShipAction::TurnLeft) {
             rotation_sign += 1.;
         }
-        if keyboard_input.pressed(KeyCode::KeyD) {
+        if ship_input.pressed(ShipAction::TurnRight) {
             rotation_sign -= 1.;
         }
         transform.rotate_z(rotation_sign * **turnrate * time.delta_seconds());
@@ -42,72 +173,99 @@ pub fn ship_turn(
 
 pub fn ship_fire(
     mut cmd: Commands,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    ship_input: Res<ShipInput>,
     mut q_ship: Query<&Children, With<PlayerShipTag>>,
     mut q_emitter: Query<(&GlobalTransform, &mut ProjectileEmission, &FireType)>,
-    fire_sound: Res<ProjectileEmitSound>,
+    mut evw_fire_effect: EventWriter<FireEffectEvent>,
+    time: Res<Time<Fixed>>,
 ) {
     // when fire key pressed
-    if keyboard_input.pressed(KeyCode::Space) {
+    if ship_input.pressed(ShipAction::Fire) {
         // find ship, get children projectile emitters
         for children in &mut q_ship {
             for child in children {
                 if let Ok((global_transform, mut emitter, firetype)) = q_emitter.get_mut(*child) {
                     // spawn primary fire projectile
-                    match firetype {
-                        FireType::Primary => {
-                            let last_emit = emitter.last_emission_time;
-
-                            if last_emit.elapsed().as_millis() as i32 >= emitter.cooldown_ms {
-                                emitter.last_emission_time = Instant::now();
-
-                                let (_scale, rotation, translation) =
-                                    global_transform.to_scale_rotation_translation();
-
-                                cmd
-                                    .spawn(ProjectileBundle::new(
-                                        translation.x,
-                                        translation.y,
-                                        Some(rotation.into()),
-                                        Some(emitter.projectile_speed),
-                                        None,
-                                        Some(emitter.damage),
-                                        None,
-                                        None,
-                                        Some(2.0),
-                                    ))
-                                    .insert(OnPlayScreen);
-                                cmd.spawn(AudioBundle {
-                                    source: fire_sound.0.clone(),
-                                    ..default()
-                                });
+                    if let FireType::Primary = firetype {
+                        let now = time.elapsed();
+                        let is_cooled_down = match emitter.last_emission_time {
+                            Some(last_emit) => {
+                                now.saturating_sub(last_emit).as_millis() as i32
+                                    >= emitter.cooldown_ms
                             }
+                            None => true,
+                        };
+
+                        if is_cooled_down {
+                            emitter.last_emission_time = Some(now);
+
+                            let (_scale, rotation, translation) =
+                                global_transform.to_scale_rotation_translation();
+
+                            cmd
+                                .spawn(ProjectileBundle::new(
+                                    translation.x,
+                                    translation.y,
+                                    Some(rotation.into()),
+                                    Some(emitter.projectile_speed),
+                                    Some(emitter.damage),
+                                    Some(2.0),
+                                    Some(*firetype),
+                                ))
+                                .insert(OnPlayScreen);
+                            evw_fire_effect.send(FireEffectEvent {
+                                transform: global_transform.compute_transform(),
+                            });
                         }
-                        _ => (),
-                    };
+                    }
                 }
             }
         }
     }
 }
 
+// How the ship behaves between thrusts. Changes the simulation, so replays record every change of it
+#[derive(Resource, Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FlightModel {
+    // ambient friction brings the ship to a stop
+    #[default]
+    Assisted,
+    // no friction, the ship keeps drifting until thrust says otherwise
+    Newtonian,
+}
+
+impl FlightModel {
+    pub fn linear_damping(self) -> f32 {
+        match self {
+            FlightModel::Assisted => AMBIENT_LINEAR_FRICTION_COEFFICIENT,
+            FlightModel::Newtonian => 0.,
+        }
+    }
+}
+
+pub fn apply_flight_model(
+    flight_model: Res<FlightModel>,
+    mut q_ship: Query<&mut Damping, With<PlayerShipTag>>,
+) {
+    let linear_damping = flight_model.linear_damping();
+    for mut damping in q_ship.iter_mut() {
+        if damping.linear_damping != linear_damping {
+            damping.linear_damping = linear_damping;
+        }
+    }
+}
+
 pub fn thrust_ship(
-    mut cmd: Commands,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    ship_input: Res<ShipInput>,
     mut evw_thrust_effect: EventWriter<ThrustEffectEvent>,
     mut q_ship: Query<(Entity, &Children, &mut ExternalForce, &Transform), With<PlayerShipTag>>,
     mut q_thruster: Query<&Thrust>,
-    thrust_sound: Res<ShipThrustSound>,
-    mut thrust_sound_stopwatch: ResMut<ShipThrustSoundStopwatch>,
-    time: Res<Time>,
 ) {
     for (ent_id, children, mut ext_force, transform) in q_ship.iter_mut() {
         // clear all external forces and torques on ship
         *ext_force = ExternalForce::default();
 
-        thrust_sound_stopwatch.0.tick(time.delta());
-
-        if keyboard_input.pressed(KeyCode::KeyS) {
+        if ship_input.pressed(ShipAction::Thrust) {
             let mut sum_forces: f32 = 0.;
             for child in children {
                 if let Ok(thruster) = q_thruster.get_mut(*child) {
@@ -118,26 +276,14 @@ pub fn thrust_ship(
             let heading: Heading = transform.rotation.into();
             ext_force.force.x += heading.x() * sum_forces;
             ext_force.force.y += heading.y() * sum_forces;
-
-            if thrust_sound_stopwatch.0.elapsed() >= Duration::from_secs_f32(0.3) {
-                thrust_sound_stopwatch.0.reset();
-                cmd.spawn(AudioBundle {
-                    source: thrust_sound.0.clone(),
-                    ..default()
-                });
-            }
         }
-        if keyboard_input.just_pressed(KeyCode::KeyS) {
-            cmd.spawn(AudioBundle {
-                source: thrust_sound.0.clone(),
-                ..default()
-            });
+        if ship_input.just_pressed(ShipAction::Thrust) {
             evw_thrust_effect.send(ThrustEffectEvent {
                 id: ent_id,
                 is_thrusting: true,
             });
         }
-        if keyboard_input.just_released(KeyCode::KeyS) {
+        if ship_input.just_released(ShipAction::Thrust) {
             evw_thrust_effect.send(ThrustEffectEvent {
                 id: ent_id,
                 is_thrusting: false,
@@ -0,0 +1,214 @@
+use bevy::{diagnostic::FrameTimeDiagnosticsPlugin, prelude::*, window::WindowResized};
+use bevy_vector_shapes::{painter::ShapePainter, shapes::LinePainter};
+
+use crate::{
+    audio::SoundVoices,
+    fps::{fps_counter_showhide, fps_text_update_system, setup_fps_counter},
+    game::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, TOP_WALL},
+};
+
+// Developer overlay toggled with `: fps counter, window resolution, live audio entities and a grid over
+// the arena
+pub fn debug_plugin(app: &mut App) {
+    app.add_plugins(FrameTimeDiagnosticsPlugin)
+        .insert_resource(ShowDebugDisplay(false))
+        .add_systems(
+            Startup,
+            (
+                setup_fps_counter,
+                setup_resolution_display,
+                setup_audio_counter,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    toggle_debug_display,
+                    update_debug_display_visibility,
+                    on_resize_system,
+                ),
+                (
+                    fps_text_update_system,
+                    fps_counter_showhide,
+                    update_audio_counter,
+                    draw_grid,
+                )
+                    .run_if(is_debug_display_on),
+            ),
+        );
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct ShowDebugDisplay(pub bool);
+
+#[derive(Component)]
+pub struct OnDebugDisplay;
+
+#[derive(Component)]
+pub struct ResolutionText;
+
+#[derive(Component)]
+pub struct AudioCounterText;
+
+pub fn is_debug_display_on(show_debug_display: Res<ShowDebugDisplay>) -> bool {
+    **show_debug_display
+}
+
+pub fn toggle_debug_display(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut show_debug_display: ResMut<ShowDebugDisplay>,
+) {
+    if keys.just_pressed(KeyCode::Backquote) {
+        **show_debug_display = !**show_debug_display;
+    }
+}
+
+// TODO all use OnDebugDisplay,
+pub fn update_debug_display_visibility(
+    show_debug_display: Res<ShowDebugDisplay>,
+    mut q_debug_visibility: Query<&mut Visibility, With<OnDebugDisplay>>,
+    // mut q_fps: Query<&mut Visibility, (Without<Node>, Without<OnDebugDisplay>, With<FpsRoot>)>,
+) {
+    if show_debug_display.is_changed() {
+        for mut vis in &mut q_debug_visibility {
+            *vis = match **show_debug_display {
+                true => Visibility::Visible,
+                false => Visibility::Hidden,
+            };
+        }
+        // let mut fps_vis = q_fps.single_mut();
+        // *fps_vis = match **show_debug_display {
+        //     true => Visibility::Visible,
+        //     _ => Visibility::Hidden,
+        // };
+    }
+}
+
+pub fn setup_resolution_display(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            background_color: BackgroundColor(Color::BLACK.with_a(0.5)),
+            z_index: ZIndex::Global(i32::MAX),
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Percent(1.),
+                top: Val::Percent(3.),
+                bottom: Val::Auto,
+                left: Val::Auto,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        },
+        OnDebugDisplay,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "Resolution",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::LIME_GREEN,
+                    ..default()
+                },
+            ),
+            ResolutionText,
+        ));
+    });
+}
+
+/// This system shows how to respond to a window being resized.
+/// Whenever the window is resized, the text will update with the new resolution.
+pub fn on_resize_system(
+    mut q: Query<&mut Text, With<ResolutionText>>,
+    mut resize_reader: EventReader<WindowResized>,
+) {
+    let mut text = q.single_mut();
+    for e in resize_reader.read() {
+        // When resolution is being changed
+        text.sections[0].value = format!("{:.1} x {:.1}", e.width, e.height);
+    }
+}
+
+pub fn setup_audio_counter(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            background_color: BackgroundColor(Color::BLACK.with_a(0.5)),
+            z_index: ZIndex::Global(i32::MAX),
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Percent(1.),
+                top: Val::Percent(5.),
+                bottom: Val::Auto,
+                left: Val::Auto,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        },
+        OnDebugDisplay,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "Audio",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::LIME_GREEN,
+                    ..default()
+                },
+            ),
+            AudioCounterText,
+        ));
+    });
+}
+
+// Every entity with an audio source, so leaked sounds show up even if they bypass the SoundPlayer
+pub fn update_audio_counter(
+    mut q_text: Query<&mut Text, With<AudioCounterText>>,
+    q_audio: Query<(), With<Handle<AudioSource>>>,
+    voices: Option<Res<SoundVoices>>,
+) {
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    let n_voices = voices.map_or(0, |voices| voices.len());
+    text.sections[0].value = format!(
+        "audio: {} entities, {} voices",
+        q_audio.iter().count(),
+        n_voices
+    );
+}
+
+pub fn draw_grid(mut painter: ShapePainter) {
+    let height = TOP_WALL - BOTTOM_WALL;
+    let width = RIGHT_WALL - LEFT_WALL;
+    let line_color = Color::rgba(1., 1., 1., 0.025);
+
+    painter.thickness = 1.;
+    painter.color = line_color;
+
+    let s = 100;
+
+    for x in (0..(width / 2.0) as usize).step_by(s) {
+        painter.line(
+            Vec3::new(x as f32, -height / 2., 0.),
+            Vec3::new(x as f32, height / 2., 0.),
+        );
+        painter.line(
+            Vec3::new(-(x as f32), -height / 2., 0.),
+            Vec3::new(-(x as f32), height / 2., 0.),
+        );
+    }
+    for y in (0..(height / 2.0) as usize).step_by(s) {
+        painter.line(
+            Vec3::new(-width / 2., y as f32, 0.),
+            Vec3::new(width / 2., y as f32, 0.),
+        );
+        painter.line(
+            Vec3::new(-width / 2., -(y as f32), 0.),
+            Vec3::new(width / 2., -(y as f32), 0.),
+        );
+    }
+}
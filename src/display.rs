@@ -0,0 +1,231 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::{ClearColorConfig, ScalingMode, Viewport},
+        view::RenderLayers,
+    },
+    window::{PrimaryWindow, WindowMode, WindowMoved, WindowResized},
+    winit::WinitWindows,
+};
+
+use crate::game::{LOGICAL_HEIGHT, LOGICAL_WIDTH};
+
+// Window modes and letterboxing. The camera always shows exactly the LOGICAL_WIDTH x LOGICAL_HEIGHT arena,
+// scaled to fit the window with black bars on the sides that don't match its aspect ratio.
+// F11 cycles windowed -> borderless fullscreen -> exclusive fullscreen, 1 / 2 / 3 pick a windowed size preset.
+pub fn display_plugin(app: &mut App) {
+    app.insert_resource(ResolutionSettings {
+        large: Vec2::new(1920., 1080.),
+        medium: Vec2::new(800., 600.),
+        small: Vec2::new(640., 360.),
+    })
+    .init_resource::<WindowGeometry>()
+    .add_systems(Startup, setup_cameras)
+    .add_systems(
+        Update,
+        (
+            fallback_to_primary_monitor,
+            remember_window_geometry,
+            (cycle_window_mode, toggle_resolution),
+            fit_camera_viewport,
+        )
+            .chain(),
+    );
+}
+
+// Render layer no game entity is on, the letterbox camera only clears the window
+const LETTERBOX_LAYER: u8 = 31;
+
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Resource)]
+pub struct ResolutionSettings {
+    pub large: Vec2,
+    pub medium: Vec2,
+    pub small: Vec2,
+}
+
+impl ResolutionSettings {
+    // smallest first
+    pub fn presets(&self) -> [Vec2; 3] {
+        [self.small, self.medium, self.large]
+    }
+}
+
+// Last size and position of the window while windowed, restored when leaving fullscreen
+#[derive(Resource, Clone, Debug, Default)]
+pub struct WindowGeometry {
+    pub position: Option<IVec2>,
+    pub size: Option<Vec2>,
+}
+
+pub fn setup_cameras(mut cmd: Commands) {
+    cmd.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: -1,
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                ..default()
+            },
+            ..default()
+        },
+        RenderLayers::layer(LETTERBOX_LAYER),
+    ));
+
+    let mut camera_bundle = Camera2dBundle::default();
+    camera_bundle.projection.scaling_mode = ScalingMode::Fixed {
+        width: LOGICAL_WIDTH,
+        height: LOGICAL_HEIGHT,
+    };
+    cmd.spawn((camera_bundle, MainCamera));
+}
+
+// Largest viewport with the arena's aspect ratio that fits the window, centered
+pub fn fit_camera_viewport(
+    q_window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut q_camera: Query<&mut Camera, With<MainCamera>>,
+) {
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    // minimized
+    if window_size.x < 1. || window_size.y < 1. {
+        return;
+    }
+    let scale = (window_size.x / LOGICAL_WIDTH).min(window_size.y / LOGICAL_HEIGHT);
+    let size = (Vec2::new(LOGICAL_WIDTH, LOGICAL_HEIGHT) * scale)
+        .round()
+        .max(Vec2::ONE);
+    let position = ((window_size - size) / 2.).floor();
+
+    for mut camera in &mut q_camera {
+        camera.viewport = Some(Viewport {
+            physical_position: position.as_uvec2(),
+            physical_size: size.as_uvec2(),
+            ..default()
+        });
+    }
+}
+
+// A monitor index that doesn't exist (e.g. from --monitor on a single monitor machine) leaves the window
+// wherever the OS puts it, center it on the primary monitor instead
+pub fn fallback_to_primary_monitor(
+    mut q_window: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+    winit_windows: NonSend<WinitWindows>,
+    mut is_checked: Local<bool>,
+) {
+    if *is_checked {
+        return;
+    }
+    let Ok((entity, mut window)) = q_window.get_single_mut() else {
+        return;
+    };
+    let Some(winit_window) = winit_windows.get_window(entity) else {
+        return;
+    };
+    *is_checked = true;
+
+    if let WindowPosition::Centered(MonitorSelection::Index(index)) = window.position {
+        let n_monitors = winit_window.available_monitors().count();
+        if index >= n_monitors {
+            warn!(
+                "monitor {} not found ({} available), using the primary monitor",
+                index, n_monitors
+            );
+            window.position = WindowPosition::Centered(MonitorSelection::Primary);
+        }
+    }
+}
+
+pub fn remember_window_geometry(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut evr_moved: EventReader<WindowMoved>,
+    mut evr_resized: EventReader<WindowResized>,
+    mut geometry: ResMut<WindowGeometry>,
+) {
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+    let is_windowed = window.mode == WindowMode::Windowed;
+    for event in evr_moved.read() {
+        if is_windowed {
+            geometry.position = Some(event.position);
+        }
+    }
+    for event in evr_resized.read() {
+        if is_windowed {
+            geometry.size = Some(Vec2::new(event.width, event.height));
+        }
+    }
+}
+
+pub fn cycle_window_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    geometry: Res<WindowGeometry>,
+) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+    let mode = next_window_mode(window.mode);
+    set_window_mode(&mut window, mode, &geometry);
+}
+
+pub fn next_window_mode(mode: WindowMode) -> WindowMode {
+    match mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        WindowMode::BorderlessFullscreen => WindowMode::Fullscreen,
+        WindowMode::Fullscreen | WindowMode::SizedFullscreen => WindowMode::Windowed,
+    }
+}
+
+pub fn set_window_mode(window: &mut Window, mode: WindowMode, geometry: &WindowGeometry) {
+    if window.mode == mode {
+        return;
+    }
+    window.mode = mode;
+    if mode == WindowMode::Windowed {
+        if let Some(size) = geometry.size {
+            window.resolution.set(size.x, size.y);
+        }
+        if let Some(position) = geometry.position {
+            window.position = WindowPosition::At(position);
+        }
+    }
+    info!("window mode {:?}", mode);
+}
+
+// Size presets switch back to a window of that size
+pub fn toggle_resolution(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    resolution: Res<ResolutionSettings>,
+    geometry: Res<WindowGeometry>,
+) {
+    let res = if keys.just_pressed(KeyCode::Digit1) {
+        resolution.small
+    } else if keys.just_pressed(KeyCode::Digit2) {
+        resolution.medium
+    } else if keys.just_pressed(KeyCode::Digit3) {
+        resolution.large
+    } else {
+        return;
+    };
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+    set_windowed_size(&mut window, res, &geometry);
+}
+
+pub fn set_windowed_size(window: &mut Window, size: Vec2, geometry: &WindowGeometry) {
+    set_window_mode(window, WindowMode::Windowed, geometry);
+    window.resolution.set(size.x, size.y);
+}
@@ -0,0 +1,560 @@
+use std::f32::consts::TAU;
+
+use bevy::{asset::LoadContext, ecs::system::SystemParam, prelude::*, utils::HashMap};
+use bevy_particle_systems::{
+    CircleSegment, ColorOverTime, Curve, CurvePoint, EmitterShape, JitteredValue, Lerp,
+    ParticleBurst, ParticleSystem, ParticleSystemBundle, Playing, ValueOverTime,
+};
+use serde::Deserialize;
+
+use crate::{
+    effects::{ParticleBudget, ParticleDensity, ParticleUsage, MIN_PARTICLE_DENSITY},
+    ron_asset::{RonAsset, RonAssetError, RonAssetLoader},
+};
+
+// Particle effects by name, each made of one or more emitters. Loaded from
+// assets/effects/default.effects.ron, see ron_asset.
+pub const EFFECT_LIBRARY_PATH: &str = "effects/default.effects.ron";
+
+pub const DEFAULT_PARTICLE_TEXTURE_PATH: &str = "images/px.png";
+
+#[derive(Asset, TypePath, Debug)]
+pub struct EffectPresets {
+    pub effects: HashMap<String, EffectPreset>,
+}
+
+impl EffectPresets {
+    pub fn get(&self, name: &str) -> Option<&EffectPreset> {
+        self.effects.get(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct EffectPreset {
+    pub priority: EffectPriority,
+    pub emitters: Vec<EmitterPreset>,
+}
+
+impl EffectPreset {
+    // Rough peak of particles alive at once, what the effect takes out of the particle budget
+    pub fn particle_estimate(&self, count: f32) -> usize {
+        self.emitters
+            .iter()
+            .map(|emitter| emitter.particle_estimate(count))
+            .sum()
+    }
+}
+
+// Which effects give way when the particle budget runs out: low ones are dropped first, high ones are
+// only ever scaled down. Low ones are also skipped with reduced effects
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EffectPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug)]
+pub struct EmitterPreset {
+    pub emitter: EmitterDef,
+    pub texture: Handle<Image>,
+}
+
+// One emitter as written in the file. Angles are in radians, directions are relative to the
+// direction the effect is spawned with
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EmitterDef {
+    pub texture: String,
+    pub max_particles: usize,
+    pub spawn_rate: ValueDef,
+    pub initial_speed: JitteredDef,
+    pub lifetime: JitteredDef,
+    pub color: ColorDef,
+    // whether EffectParams::tint applies, e.g. not to a flash that should stay white
+    pub tinted: bool,
+    pub shape: ShapeDef,
+    pub looping: bool,
+    pub rotate_to_movement_direction: bool,
+    pub initial_rotation: JitteredDef,
+    pub duration: f32,
+    pub max_distance: Option<f32>,
+    pub scale: ValueDef,
+    pub bursts: Vec<BurstDef>,
+}
+
+impl Default for EmitterDef {
+    fn default() -> Self {
+        Self {
+            texture: DEFAULT_PARTICLE_TEXTURE_PATH.to_string(),
+            max_particles: 100,
+            spawn_rate: ValueDef::Constant(0.),
+            initial_speed: JitteredDef::default(),
+            lifetime: JitteredDef {
+                value: 1.,
+                jitter: (0., 0.),
+            },
+            color: ColorDef::Constant((1., 1., 1., 1.)),
+            tinted: true,
+            shape: ShapeDef::default(),
+            looping: false,
+            rotate_to_movement_direction: false,
+            initial_rotation: JitteredDef::default(),
+            duration: 1.,
+            max_distance: None,
+            scale: ValueDef::Constant(1.),
+            bursts: Vec::new(),
+        }
+    }
+}
+
+// Value with a random offset in jitter.0..jitter.1 per particle
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct JitteredDef {
+    pub value: f32,
+    pub jitter: (f32, f32),
+}
+
+impl JitteredDef {
+    fn scaled(self, factor: f32) -> JitteredValue {
+        match self.jitter.0 < self.jitter.1 {
+            true => JitteredValue::jittered(
+                self.value * factor,
+                (self.jitter.0 * factor)..(self.jitter.1 * factor),
+            ),
+            false => (self.value * factor).into(),
+        }
+    }
+}
+
+// Value over the lifetime of the emitter
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum ValueDef {
+    Constant(f32),
+    Lerp(f32, f32),
+}
+
+impl ValueDef {
+    fn mean(self) -> f32 {
+        match self {
+            ValueDef::Constant(value) => value,
+            ValueDef::Lerp(a, b) => (a + b) / 2.,
+        }
+    }
+
+    fn scaled(self, factor: f32) -> ValueOverTime {
+        match self {
+            ValueDef::Constant(value) => (value * factor).into(),
+            ValueDef::Lerp(a, b) => ValueOverTime::Lerp(Lerp::new(a * factor, b * factor)),
+        }
+    }
+}
+
+// Colors are (r, g, b, a), gradient points are (time 0..=1 over the particle's lifetime, color)
+#[derive(Deserialize, Clone, Debug)]
+pub enum ColorDef {
+    Constant((f32, f32, f32, f32)),
+    Gradient(Vec<(f32, (f32, f32, f32, f32))>),
+}
+
+impl ColorDef {
+    fn tinted(&self, tint: Color) -> ColorOverTime {
+        let color = |(r, g, b, a): (f32, f32, f32, f32)| {
+            Color::rgba(r * tint.r(), g * tint.g(), b * tint.b(), a * tint.a())
+        };
+        match self {
+            ColorDef::Constant(rgba) => ColorOverTime::Constant(color(*rgba)),
+            ColorDef::Gradient(points) => ColorOverTime::Gradient(Curve::new(
+                points
+                    .iter()
+                    .map(|(at, rgba)| CurvePoint::new(color(*rgba), *at))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum ShapeDef {
+    CircleSegment {
+        #[serde(default)]
+        radius: f32,
+        #[serde(default = "full_circle")]
+        opening_angle: f32,
+        #[serde(default)]
+        direction_angle: f32,
+    },
+    Line {
+        length: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+}
+
+impl Default for ShapeDef {
+    fn default() -> Self {
+        ShapeDef::CircleSegment {
+            radius: 0.,
+            opening_angle: TAU,
+            direction_angle: 0.,
+        }
+    }
+}
+
+fn full_circle() -> f32 {
+    TAU
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct BurstDef {
+    pub time: f32,
+    pub count: usize,
+}
+
+// What varies between spawns of the same effect
+#[derive(Clone, Copy, Debug)]
+pub struct EffectParams {
+    // radians, added to every emitter's direction
+    pub direction: f32,
+    // multiplies particle counts and spawn rates, particle speeds and lifetimes grow with its square root
+    pub intensity: f32,
+    // multiplies the emitter's extent, particle speeds, distances and particle scale
+    pub size: f32,
+    // multiplies every color
+    pub tint: Color,
+}
+
+impl Default for EffectParams {
+    fn default() -> Self {
+        Self {
+            direction: 0.,
+            intensity: 1.,
+            size: 1.,
+            tint: Color::WHITE,
+        }
+    }
+}
+
+impl EffectParams {
+    pub fn with_direction(mut self, direction: f32) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+}
+
+impl EmitterPreset {
+    // Bursts plus what the spawn rate adds over the emitter's duration, at most max_particles
+    pub fn particle_estimate(&self, count: f32) -> usize {
+        let emitter = &self.emitter;
+        let bursts: usize = emitter.bursts.iter().map(|burst| burst.count).sum();
+        let spawned = bursts as f32 + emitter.spawn_rate.mean() * emitter.duration;
+        ((spawned * count).ceil() as usize).min(emitter.max_particles)
+    }
+
+    // count scales particle counts, it's the effect's intensity times the particle density
+    pub fn particle_system(&self, params: &EffectParams, count: f32) -> ParticleSystem {
+        let emitter = &self.emitter;
+        let vigor = params.intensity.max(0.).sqrt();
+        let scale_count = |n: usize| ((n as f32 * count).ceil() as usize).max(1);
+        let emitter_shape = match emitter.shape {
+            ShapeDef::CircleSegment {
+                radius,
+                opening_angle,
+                direction_angle,
+            } => EmitterShape::CircleSegment(CircleSegment {
+                radius: (radius * params.size).into(),
+                opening_angle,
+                direction_angle: direction_angle + params.direction,
+            }),
+            ShapeDef::Line { length, angle } => {
+                EmitterShape::line(length * params.size, angle + params.direction)
+            }
+        };
+        ParticleSystem {
+            max_particles: scale_count(emitter.max_particles),
+            texture: self.texture.clone().into(),
+            spawn_rate_per_second: emitter.spawn_rate.scaled(count),
+            initial_speed: emitter.initial_speed.scaled(params.size * vigor),
+            lifetime: emitter.lifetime.scaled(vigor),
+            color: match emitter.tinted {
+                true => emitter.color.tinted(params.tint),
+                false => emitter.color.tinted(Color::WHITE),
+            },
+            emitter_shape,
+            looping: emitter.looping,
+            // one-shot effects clean up after themselves
+            despawn_on_finish: !emitter.looping,
+            rotate_to_movement_direction: emitter.rotate_to_movement_direction,
+            initial_rotation: emitter.initial_rotation.scaled(1.),
+            system_duration_seconds: emitter.duration,
+            max_distance: emitter.max_distance.map(|distance| distance * params.size),
+            scale: emitter.scale.scaled(params.size),
+            bursts: emitter
+                .bursts
+                .iter()
+                .map(|burst| ParticleBurst::new(burst.time, scale_count(burst.count)))
+                .collect(),
+            ..ParticleSystem::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EffectPresetDef {
+    #[serde(default)]
+    priority: EffectPriority,
+    emitters: Vec<EmitterDef>,
+}
+
+#[derive(Deserialize)]
+pub struct EffectPresetsDef {
+    effects: HashMap<String, EffectPresetDef>,
+}
+
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct EffectLibrary(pub Handle<EffectPresets>);
+
+impl RonAsset for EffectPresets {
+    type Def = EffectPresetsDef;
+
+    const EXTENSIONS: &'static [&'static str] = &["effects.ron"];
+
+    fn from_def(
+        def: EffectPresetsDef,
+        load_context: &mut LoadContext,
+    ) -> Result<Self, RonAssetError> {
+        let effects = def
+            .effects
+            .into_iter()
+            .map(|(name, preset)| {
+                let emitters = preset
+                    .emitters
+                    .into_iter()
+                    .map(|emitter| EmitterPreset {
+                        texture: load_context.load(emitter.texture.clone()),
+                        emitter,
+                    })
+                    .collect();
+                let priority = preset.priority;
+                (name, EffectPreset { priority, emitters })
+            })
+            .collect();
+        Ok(EffectPresets { effects })
+    }
+}
+
+// Looping effect that lives on its entity instead of being spawned and left to finish, e.g. the ship's
+// thruster. Gameplay only names it, the particle system is attached once the library is loaded and
+// rebuilt when it's reloaded. The entity carries a single particle system, so the preset must have
+// exactly one emitter
+#[derive(Component, Clone, Debug)]
+pub struct PersistentEffect {
+    pub name: String,
+    pub params: EffectParams,
+}
+
+impl PersistentEffect {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            params: EffectParams::default(),
+        }
+    }
+
+    pub fn with_params(mut self, params: EffectParams) -> Self {
+        self.params = params;
+        self
+    }
+}
+
+// On a PersistentEffect whose preset is missing or unusable, so it's warned about once instead of every
+// frame. Taken off when the library is reloaded, to try again
+#[derive(Component)]
+pub struct InvalidPersistentEffect;
+
+#[derive(SystemParam)]
+pub struct EffectSpawner<'w, 's> {
+    cmd: Commands<'w, 's>,
+    library: Res<'w, EffectLibrary>,
+    presets: Res<'w, Assets<EffectPresets>>,
+    density: Res<'w, ParticleDensity>,
+    budget: Res<'w, ParticleBudget>,
+    usage: ResMut<'w, ParticleUsage>,
+}
+
+// Below this fraction of its particles, an effect over budget isn't worth showing unless it's high
+// priority
+pub const MIN_BUDGET_FRACTION: f32 = 0.25;
+
+impl<'w, 's> EffectSpawner<'w, 's> {
+    // Particle system of a persistent effect, None until the library is loaded, an error if there's no
+    // such effect or it doesn't have exactly one emitter
+    pub fn persistent_particle_system(
+        &self,
+        effect: &PersistentEffect,
+    ) -> Option<Result<ParticleSystem, String>> {
+        let presets = self.presets.get(&self.library.0)?;
+        let Some(preset) = presets.get(&effect.name) else {
+            return Some(Err(format!("no effect preset named {}", effect.name)));
+        };
+        let [emitter] = preset.emitters.as_slice() else {
+            return Some(Err(format!(
+                "effect preset {} has {} emitters, persistent effects take exactly one",
+                effect.name,
+                preset.emitters.len()
+            )));
+        };
+        Some(Ok(
+            emitter.particle_system(&effect.params, self.count(&effect.params))
+        ))
+    }
+
+    fn count(&self, params: &EffectParams) -> f32 {
+        params.intensity.max(0.) * self.density.clamp(MIN_PARTICLE_DENSITY, 1.)
+    }
+
+    // Spawns every emitter of the effect at transform, playing right away. Comes out of the particle
+    // budget: scaled down to what's left of it, or dropped when too little is left and the effect isn't
+    // high priority
+    pub fn spawn_effect(&mut self, name: &str, transform: Transform, params: EffectParams) {
+        let mut count = self.count(&params);
+        let Some(presets) = self.presets.get(&self.library.0) else {
+            return;
+        };
+        let Some(preset) = presets.get(name) else {
+            warn!("no effect preset named {}", name);
+            return;
+        };
+        if self.budget.reduced_effects && preset.priority == EffectPriority::Low {
+            return;
+        }
+        let wanted = preset.particle_estimate(count);
+        let available = self.budget.limit().saturating_sub(self.usage.total());
+        if wanted > available {
+            let fraction = available as f32 / wanted as f32;
+            if fraction < MIN_BUDGET_FRACTION && preset.priority < EffectPriority::High {
+                debug!("particle budget spent, dropped {}", name);
+                return;
+            }
+            count *= fraction.max(MIN_BUDGET_FRACTION);
+        }
+        self.usage.reserved += preset.particle_estimate(count);
+        for particle_system in preset_particle_systems(preset, &params, count) {
+            self.cmd.spawn((
+                ParticleSystemBundle {
+                    particle_system,
+                    transform,
+                    // emit from the right place on the first frame, before transforms propagate
+                    global_transform: transform.into(),
+                    ..ParticleSystemBundle::default()
+                },
+                Playing,
+            ));
+        }
+    }
+}
+
+fn preset_particle_systems(
+    preset: &EffectPreset,
+    params: &EffectParams,
+    count: f32,
+) -> Vec<ParticleSystem> {
+    preset
+        .emitters
+        .iter()
+        .map(|emitter| emitter.particle_system(params, count))
+        .collect()
+}
+
+pub fn effect_library_plugin(app: &mut App) {
+    app.init_asset::<EffectPresets>()
+        .init_asset_loader::<RonAssetLoader<EffectPresets>>()
+        .add_systems(Startup, load_effect_library)
+        .add_systems(
+            Update,
+            (rebuild_persistent_effects, attach_persistent_effects).chain(),
+        );
+}
+
+pub fn load_effect_library(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(EffectLibrary(asset_server.load(EFFECT_LIBRARY_PATH)));
+}
+
+#[allow(clippy::type_complexity)]
+pub fn attach_persistent_effects(
+    mut effects: EffectSpawner,
+    q_effects: Query<
+        (Entity, &PersistentEffect, &Transform),
+        (Without<ParticleSystem>, Without<InvalidPersistentEffect>),
+    >,
+) {
+    for (entity, effect, transform) in &q_effects {
+        let particle_system = match effects.persistent_particle_system(effect) {
+            None => return,
+            Some(Ok(particle_system)) => particle_system,
+            Some(Err(e)) => {
+                warn!("{}", e);
+                effects.cmd.entity(entity).insert(InvalidPersistentEffect);
+                continue;
+            }
+        };
+        // keeps whatever else is on the entity, Playing included
+        effects.cmd.entity(entity).insert(ParticleSystemBundle {
+            particle_system,
+            transform: *transform,
+            ..ParticleSystemBundle::default()
+        });
+    }
+}
+
+// Applies a reloaded library or a new particle density to the effects already attached
+pub fn rebuild_persistent_effects(
+    mut evr_assets: EventReader<AssetEvent<EffectPresets>>,
+    mut effects: EffectSpawner,
+    mut q_effects: Query<(&PersistentEffect, &mut ParticleSystem)>,
+    q_invalid: Query<Entity, With<InvalidPersistentEffect>>,
+) {
+    let mut is_reloaded = false;
+    for event in evr_assets.read() {
+        if event.is_modified(&effects.library.0) {
+            info!("reloaded effect presets");
+            is_reloaded = true;
+        }
+    }
+    if is_reloaded {
+        for entity in &q_invalid {
+            effects
+                .cmd
+                .entity(entity)
+                .remove::<InvalidPersistentEffect>();
+        }
+    }
+    if !is_reloaded && !effects.density.is_changed() {
+        return;
+    }
+    for (effect, mut particle_system) in &mut q_effects {
+        // an effect broken by the reload keeps playing as it was
+        match effects.persistent_particle_system(effect) {
+            Some(Ok(rebuilt)) => *particle_system = rebuilt,
+            Some(Err(e)) if is_reloaded => warn!("{}", e),
+            _ => {}
+        }
+    }
+}
@@ -1,30 +1,30 @@
-use std::f32::consts::PI;
+use std::f32::consts::{PI, TAU};
 
 use bevy::prelude::*;
-use bevy_particle_systems::{
-    CircleSegment, ColorOverTime, Curve, CurvePoint, EmitterShape, JitteredValue, Lerp,
-    ParticleBurst, ParticleSystem, ParticleSystemBundle, Playing, ValueOverTime,
+use bevy_particle_systems::{ParticleCount, ParticleSystem, Playing};
+use bevy_rapier2d::{
+    dynamics::{Damping, GravityScale, RigidBody, Velocity},
+    geometry::{Collider, Restitution},
 };
-use bevy_rapier2d::dynamics::Velocity;
+use rand::Rng;
 
 use crate::{
-    audio::{
-        AsteroidClashSound, AsteroidDestroyedSound, ProjectileImpactSound, ShipDamagedSound,
-        VesselDestroyedSound,
-    },
     avatars::Thrust,
     components::{CollisionRadius, PlayerShipTag},
+    effect_library::{EffectParams, EffectSpawner},
     events::Avatars,
-    game::ParticlePixelTexture,
+    game::{OnPlayScreen, INIT_SHIP_MOVE_SPEED, MEDIUM_ASTEROID_R},
+    physics::debris_collision_groups,
 };
 
-// Produce effects in update schedule: collision/death sounds and particles
+// Produce effects in update schedule: collision/death particles. Sounds for the same events are in audio.rs
+// The particle effects themselves are presets in assets/effects, see effect_library.rs
 
 // CollisionEffectEvent is a low broad early development event created to handle data passing between FixedUpdate collisions and Update collision effects
 // These are generaly perceivable effects generated from the interaction (collision) between two avatars (entity consisting of a perceivable, interactable game object aka game actor)
 // I expect this event to be refactored as needed, with higher or lower granularity as development proceeds
 // For now (5/1/2024), I want to minimize making many systems and adding overt complications to game systems code
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct CollisionEffectEvent {
     pub avatar_a: Avatars,
     pub ent_a: Option<Entity>,
@@ -34,6 +34,7 @@ pub struct CollisionEffectEvent {
     pub avatar_b: Option<Avatars>,
     pub ent_b: Option<Entity>,
     pub transform_b: Option<Transform>,
+    pub impact: Impact,
 }
 
 impl Default for CollisionEffectEvent {
@@ -47,14 +48,108 @@ impl Default for CollisionEffectEvent {
             avatar_b: None,
             ent_b: None,
             transform_b: None,
+            impact: Impact::default(),
         }
     }
 }
 
-#[derive(Event)]
+impl CollisionEffectEvent {
+    // Where the collision happened, between the two bodies when both are known
+    pub fn position(&self) -> Option<Vec3> {
+        match (self.transform_a, self.transform_b) {
+            (Some(a), Some(b)) => Some(a.translation.lerp(b.translation, 0.5)),
+            (a, b) => a.or(b).map(|transform| transform.translation),
+        }
+    }
+
+    // How hard the hit was compared to a typical one of its kind, effects and sounds scale with it
+    pub fn intensity(&self) -> f32 {
+        let typical = match (self.avatar_a, self.avatar_b) {
+            (Avatars::Projectile, _) => &TYPICAL_PROJECTILE_HIT,
+            (Avatars::Asteroid, Some(Avatars::Asteroid)) => &TYPICAL_ASTEROID_CLASH,
+            _ => &TYPICAL_SHIP_HIT,
+        };
+        self.impact.intensity(typical)
+    }
+}
+
+// How hard two bodies hit each other. Speeds are in pixels per second, masses and forces in rapier's
+// units (pixels_per_meter 2)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Impact {
+    pub relative_speed: Option<f32>,
+    // reduced mass of the pair, m_a * m_b / (m_a + m_b)
+    pub mass: Option<f32>,
+    pub contact_force: Option<f32>,
+}
+
+// Hits that effects and sounds are authored for. A projectile weighs ~10, the ship ~90 and a medium
+// asteroid ~3500, the force is about what stops the reduced mass at that speed within one 64Hz tick
+pub const TYPICAL_PROJECTILE_HIT: Impact = Impact {
+    relative_speed: Some(500.),
+    mass: Some(10.),
+    contact_force: Some(160_000.),
+};
+pub const TYPICAL_ASTEROID_CLASH: Impact = Impact {
+    relative_speed: Some(60.),
+    mass: Some(1800.),
+    contact_force: Some(4_500_000.),
+};
+pub const TYPICAL_SHIP_HIT: Impact = Impact {
+    relative_speed: Some(150.),
+    mass: Some(90.),
+    contact_force: Some(650_000.),
+};
+pub const MIN_EFFECT_INTENSITY: f32 = 0.25;
+pub const MAX_EFFECT_INTENSITY: f32 = 3.;
+
+impl Impact {
+    // Grows with the square root of the momentum involved compared to the typical hit: the contact
+    // force when the solver reported one, else relative speed times mass
+    pub fn intensity(&self, typical: &Impact) -> f32 {
+        let ratio = |value: Option<f32>, typical: Option<f32>| Some(value? / typical?.max(1e-6));
+        let momentum = ratio(self.contact_force, typical.contact_force)
+            .filter(|force| *force > 0.)
+            .or_else(|| {
+                let speed = ratio(self.relative_speed, typical.relative_speed)?;
+                Some(speed * ratio(self.mass, typical.mass).unwrap_or(1.))
+            });
+        momentum
+            .map_or(1., f32::sqrt)
+            .clamp(MIN_EFFECT_INTENSITY, MAX_EFFECT_INTENSITY)
+    }
+}
+
+#[derive(Event, Clone)]
 pub struct DestructionEffectEvent {
     pub transform: Transform,
     pub avatar: Avatars,
+    pub velocity: Option<Velocity>,
+    pub collision_radius: Option<CollisionRadius>,
+    pub material: Option<Handle<ColorMaterial>>,
+}
+
+impl DestructionEffectEvent {
+    // Bigger the faster it was going, never smaller than as authored
+    //TODO scale with ship's contained/accumulated energy, aka spirit energy aka prana aka chi
+    pub fn intensity(&self) -> f32 {
+        let speed = self.velocity.map_or(0., |velocity| velocity.linvel.length());
+        (1. + speed / INIT_SHIP_MOVE_SPEED)
+            .sqrt()
+            .min(MAX_EFFECT_INTENSITY)
+    }
+}
+
+impl Default for DestructionEffectEvent {
+    fn default() -> Self {
+        Self {
+            transform: Transform::default(),
+            avatar: Avatars::Other,
+            velocity: None,
+            collision_radius: None,
+            material: None,
+        }
+    }
 }
 
 #[derive(Event)]
@@ -63,150 +158,264 @@ pub struct ThrustEffectEvent {
     pub is_thrusting: bool,
 }
 
+#[derive(Event)]
+pub struct FireEffectEvent {
+    pub transform: Transform,
+}
+
+// Fraction of particles emitted (0..=1), from the particle density setting
+#[derive(Resource, Deref, DerefMut, PartialEq)]
+pub struct ParticleDensity(pub f32);
+
+impl Default for ParticleDensity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+pub const MIN_PARTICLE_DENSITY: f32 = 0.1;
+
+pub const DEFAULT_PARTICLE_BUDGET: usize = 4000;
+// Fraction of the budget left with reduced effects
+pub const REDUCED_EFFECTS_BUDGET: f32 = 0.25;
+
+// Most particles alive at once across every effect, from the particle budget and reduced effects
+// settings. Over budget, new effects are scaled down or dropped by priority, see EffectSpawner. Reduced
+// effects also skips low priority effects and asteroid debris altogether
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct ParticleBudget {
+    pub max_particles: usize,
+    pub reduced_effects: bool,
+}
+
+impl Default for ParticleBudget {
+    fn default() -> Self {
+        Self {
+            max_particles: DEFAULT_PARTICLE_BUDGET,
+            reduced_effects: false,
+        }
+    }
+}
+
+impl ParticleBudget {
+    pub fn limit(&self) -> usize {
+        match self.reduced_effects {
+            true => (self.max_particles as f32 * REDUCED_EFFECTS_BUDGET) as usize,
+            false => self.max_particles,
+        }
+    }
+}
+
+// Particles alive across every particle system as of the start of the frame, plus those promised to
+// effects spawned since. Systems spawned late in a frame may not have emitted by the next count, so
+// a burst can briefly go uncounted
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ParticleUsage {
+    pub live: usize,
+    pub reserved: usize,
+}
+
+impl ParticleUsage {
+    pub fn total(&self) -> usize {
+        self.live + self.reserved
+    }
+}
+
+pub fn count_live_particles(q_counts: Query<&ParticleCount>, mut usage: ResMut<ParticleUsage>) {
+    usage.live = q_counts.iter().map(|count| count.0).sum();
+    usage.reserved = 0;
+}
+
+// Shards per unit of radius of a destroyed asteroid, flung out at DEBRIS_MIN_SPEED..DEBRIS_MAX_SPEED on
+// top of the asteroid's own velocity
+pub const DEBRIS_SHARDS_PER_RADIUS: f32 = 0.2;
+pub const DEBRIS_MIN_SPEED: f32 = 40.;
+pub const DEBRIS_MAX_SPEED: f32 = 140.;
+pub const DEBRIS_MIN_LENGTH: f32 = 3.;
+pub const DEBRIS_MAX_LENGTH: f32 = 7.;
+pub const DEBRIS_LIFETIME_SECS: f32 = 1.5;
+
+// Short-lived physical shard of a destroyed asteroid, fades out over its lifetime and despawns
+#[derive(Component)]
+pub struct Debris {
+    pub lifetime: Timer,
+    pub color: Color,
+}
+
 pub fn handle_collision_effects(
-    mut cmd: Commands,
     mut evr_coll_effects: EventReader<CollisionEffectEvent>,
-    particle_pixel_texture: Res<ParticlePixelTexture>,
-    proj_coll_sound: Res<ProjectileImpactSound>,
-    damage_ship_sound: Res<ShipDamagedSound>,
-    asteroid_clash_sound: Res<AsteroidClashSound>,
+    mut effects: EffectSpawner,
 ) {
     for event in evr_coll_effects.read() {
         match event.avatar_a {
             Avatars::Projectile => {
-                // play sound, emit particles
                 emit_projectile_collision_particles(
-                    &mut cmd,
-                    &particle_pixel_texture,
+                    &mut effects,
                     &event.transform_a.unwrap_or_default(),
                     &event.velocity_a.unwrap_or_default(),
+                    event.intensity(),
                 );
-                cmd.spawn(AudioBundle {
-                    source: proj_coll_sound.0.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
             }
             Avatars::PlayerShip => {
-                emit_ship_collision_particles(
-                    &mut cmd,
-                    &event.transform_a.unwrap_or_default(),
-                    &particle_pixel_texture,
+                effects.spawn_effect(
+                    "ship_hit",
+                    flat(&event.transform_a.unwrap_or_default()),
+                    EffectParams::default().with_intensity(event.intensity()),
                 );
-                cmd.spawn(AudioBundle {
-                    source: damage_ship_sound.0.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
             }
             Avatars::Asteroid => {
-                match event.avatar_b {
-                    Some(Avatars::Asteroid) => {
-                        // emit clash parts
-                        cmd.spawn(AudioBundle {
-                            source: asteroid_clash_sound.0.clone(),
-                            settings: PlaybackSettings::DESPAWN,
-                        });
-                        emit_asteroid_w_asteroid_collision_particles(
-                            &mut cmd,
-                            &particle_pixel_texture,
-                            &event.transform_a.unwrap(),
-                            &event.collision_radius_a.unwrap(),
-                            &event.transform_b.unwrap(),
-                        );
-                    }
-                    _ => {}
+                if let Some(Avatars::Asteroid) = event.avatar_b {
+                    // emit clash parts
+                    emit_asteroid_w_asteroid_collision_particles(
+                        &mut effects,
+                        &event.transform_a.unwrap(),
+                        &event.collision_radius_a.unwrap(),
+                        &event.transform_b.unwrap(),
+                        event.intensity(),
+                    );
                 }
                 // particles asteroid-asteroid collision
-                // sound clash
             }
             _ => {}
         }
     }
 }
 
+// Effects play in the plane of the background, whatever the depth of what caused them
+fn flat(transform: &Transform) -> Transform {
+    Transform::from_xyz(transform.translation.x, transform.translation.y, 0.0)
+}
+
 fn emit_projectile_collision_particles(
-    cmd: &mut Commands,
-    particle_pixel_texture: &Res<ParticlePixelTexture>,
+    effects: &mut EffectSpawner,
     transform: &Transform,
     velocity: &Velocity,
+    intensity: f32,
 ) {
     let direction_angle = Vec2::from_angle(PI).rotate(velocity.linvel).to_angle();
-    cmd
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: 6,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: 0.0.into(),
-                // TODO scale with proj velocity
-                initial_speed: JitteredValue::jittered(30.0, -10.0..0.0),
-                lifetime: JitteredValue::jittered(0.5, -0.25..0.0),
-                // color: ColorOverTime::Constant((Color::WHITE)),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::WHITE, 0.0),
-                    CurvePoint::new(Color::rgba(1., 1., 1., 0.5), 0.2),
-                    CurvePoint::new(Color::rgba(1.0, 1.0, 1.0, 0.1), 1.0),
-                ])),
-                emitter_shape: CircleSegment {
-                    radius: 0.0.into(),
-                    opening_angle: std::f32::consts::PI / 2.0,
-                    direction_angle,
-                }
-                .into(),
-                looping: false,
-                rotate_to_movement_direction: true,
-                initial_rotation: (0_f32).to_radians().into(),
-                system_duration_seconds: 0.25,
-                max_distance: Some(100.0),
-                scale: 1.0.into(),
-                bursts: vec![ParticleBurst::new(0.0, 6)],
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(transform.translation.x, transform.translation.y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing);
+    effects.spawn_effect(
+        "projectile_impact",
+        flat(transform),
+        EffectParams::default()
+            .with_direction(direction_angle)
+            .with_intensity(intensity),
+    );
 }
 
 pub fn handle_destruction_effects(
     mut cmd: Commands,
     mut ev_w: EventReader<DestructionEffectEvent>,
-    destroy_asteroid_sound: Res<AsteroidDestroyedSound>,
-    destroy_vessel_sound: Res<VesselDestroyedSound>,
-    damage_ship_sound: Res<ShipDamagedSound>,
-    particle_pixel_texture: Res<ParticlePixelTexture>,
+    mut effects: EffectSpawner,
+    materials: Res<Assets<ColorMaterial>>,
+    budget: Res<ParticleBudget>,
 ) {
     for event in ev_w.read() {
         match event.avatar {
             Avatars::PlayerShip => {
-                // Post v1.0: astral/cosmic/etheral body gameplay
-                // commands.spawn(AudioBundle {
-                //     source: destroy_soul_sound.0.clone(),
-                //     settings: PlaybackSettings::DESPAWN,
-                // });
-                cmd.spawn(AudioBundle {
-                    source: destroy_vessel_sound.0.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-                cmd.spawn(AudioBundle {
-                    source: damage_ship_sound.0.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-                emit_ship_destruction_particles(
-                    &mut cmd,
-                    &event.transform,
-                    &particle_pixel_texture,
+                effects.spawn_effect(
+                    "ship_destroyed",
+                    flat(&event.transform),
+                    EffectParams::default().with_intensity(event.intensity()),
                 );
             }
             Avatars::Asteroid => {
-                cmd.spawn(AudioBundle {
-                    source: destroy_asteroid_sound.0.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
+                let radius = event.collision_radius.map_or(MEDIUM_ASTEROID_R, |r| r.0);
+                let color = event
+                    .material
+                    .as_ref()
+                    .and_then(|material| materials.get(material))
+                    .map_or(Color::WHITE, |material| material.color);
+                // dust and flash, sized for a medium asteroid
+                let size = radius / MEDIUM_ASTEROID_R;
+                effects.spawn_effect(
+                    "asteroid_destroyed",
+                    flat(&event.transform),
+                    EffectParams::default()
+                        .with_size(size)
+                        .with_intensity(size)
+                        .with_tint(color),
+                );
+                if !budget.reduced_effects {
+                    emit_asteroid_debris(
+                        &mut cmd,
+                        &event.transform,
+                        radius,
+                        &event.velocity.unwrap_or_default(),
+                        color,
+                    );
+                }
             }
             _ => {}
         }
     }
 }
 
+// Shards start spread around the middle of the asteroid so they don't start inside each other
+pub fn emit_asteroid_debris(
+    cmd: &mut Commands,
+    transform: &Transform,
+    radius: f32,
+    velocity: &Velocity,
+    color: Color,
+) {
+    let mut rng = rand::thread_rng();
+    let n_shards = (radius * DEBRIS_SHARDS_PER_RADIUS).round().max(1.) as usize;
+    for i in 0..n_shards {
+        let angle = TAU * (i as f32 + rng.gen::<f32>() * 0.5) / n_shards as f32;
+        let direction = Vec2::from_angle(angle);
+        let position = transform.translation.truncate() + direction * radius * 0.5;
+        let length = rng.gen_range(DEBRIS_MIN_LENGTH..DEBRIS_MAX_LENGTH);
+        let speed = rng.gen_range(DEBRIS_MIN_SPEED..DEBRIS_MAX_SPEED);
+        let lifetime = DEBRIS_LIFETIME_SECS * rng.gen_range(0.6..1.);
+        cmd.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(length, length * 0.5)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(transform.translation.z))
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::cuboid(length * 0.5, length * 0.25),
+            debris_collision_groups(),
+            Velocity {
+                linvel: velocity.linvel + direction * speed,
+                angvel: rng.gen_range(-8.0..8.0),
+            },
+            GravityScale(0.),
+            Damping {
+                linear_damping: 0.5,
+                angular_damping: 0.5,
+            },
+            Restitution::coefficient(0.5),
+            Debris {
+                lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+                color,
+            },
+            OnPlayScreen,
+        ));
+    }
+}
+
+pub fn fade_debris(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut q_debris: Query<(Entity, &mut Debris, &mut Sprite)>,
+) {
+    for (entity, mut debris, mut sprite) in &mut q_debris {
+        if debris.lifetime.tick(time.delta()).finished() {
+            cmd.entity(entity).despawn();
+            continue;
+        }
+        sprite.color = debris
+            .color
+            .with_a(debris.color.a() * debris.lifetime.fraction_remaining());
+    }
+}
+
 pub fn handle_thrust_effects(
     mut cmd: Commands,
     mut evr_thrust_effect: EventReader<ThrustEffectEvent>,
@@ -270,152 +479,19 @@ pub fn handle_thrust_effects(
 // }
 
 fn emit_asteroid_w_asteroid_collision_particles(
-    cmd: &mut Commands,
-    particle_pixel_texture: &ParticlePixelTexture,
+    effects: &mut EffectSpawner,
     transform_a: &Transform,
     r_a: &CollisionRadius,
     transform_b: &Transform,
+    intensity: f32,
 ) {
     let normalized = (transform_b.translation - transform_a.translation).normalize();
-    let pos_perpendicular = Vec2::new(normalized.x, normalized.y).to_angle() + PI / 2.0;
-    let neg_perpendicular = Vec2::new(normalized.x, normalized.y).to_angle() - PI / 2.0;
     let collision_pt = transform_a.translation + normalized * r_a.0;
-
-    cmd
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: 200,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: 10.0.into(),
-                initial_speed: JitteredValue::jittered(20.0, -15.0..10.0),
-                lifetime: JitteredValue::jittered(2.0, -0.5..0.5),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::WHITE, 0.0),
-                    CurvePoint::new(Color::rgba(1., 1., 1., 0.5), 0.2),
-                    CurvePoint::new(Color::rgba(1.0, 1.0, 1.0, 0.1), 1.0),
-                ])),
-                emitter_shape: CircleSegment {
-                    radius: 0.0.into(),
-                    opening_angle: std::f32::consts::PI / 16.,
-                    direction_angle: pos_perpendicular,
-                }
-                .into(),
-                looping: false,
-                rotate_to_movement_direction: true,
-                initial_rotation: (0_f32).to_radians().into(),
-                system_duration_seconds: 0.25,
-                max_distance: Some(100.0),
-                scale: 1.0.into(),
-                bursts: vec![ParticleBurst::new(0.0, 10)],
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(collision_pt.x, collision_pt.y, 0.0),
-            global_transform: GlobalTransform::from_xyz(collision_pt.x, collision_pt.y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing);
-    cmd
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: 200,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: 10.0.into(),
-                initial_speed: JitteredValue::jittered(20.0, -15.0..10.0),
-                lifetime: JitteredValue::jittered(2.0, -0.5..0.5),
-                // color: ColorOverTime::Constant((Color::WHITE)),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::WHITE, 0.0),
-                    CurvePoint::new(Color::rgba(1., 1., 1., 0.5), 0.2),
-                    CurvePoint::new(Color::rgba(1.0, 1.0, 1.0, 0.1), 1.0),
-                ])),
-                emitter_shape: CircleSegment {
-                    radius: 0.0.into(),
-                    opening_angle: std::f32::consts::PI / 16.,
-                    direction_angle: neg_perpendicular,
-                }
-                .into(),
-                looping: false,
-                rotate_to_movement_direction: true,
-                initial_rotation: (0_f32).to_radians().into(),
-                system_duration_seconds: 0.25,
-                max_distance: Some(100.0),
-                scale: 1.0.into(),
-                bursts: vec![ParticleBurst::new(0.0, 10)],
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(collision_pt.x, collision_pt.y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing);
-}
-
-fn emit_ship_collision_particles(
-    cmd: &mut Commands,
-    transform: &Transform,
-    particle_pixel_texture: &ParticlePixelTexture,
-) {
-    cmd
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: 25,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: 0.0.into(),
-                initial_speed: JitteredValue::jittered(175.0, -50.0..0.0),
-                lifetime: JitteredValue::jittered(3.0, -0.5..0.0),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::RED, 0.0),
-                    CurvePoint::new(Color::BLACK, 0.5),
-                    CurvePoint::new(Color::BLACK, 1.0),
-                ])),
-                looping: false,
-                system_duration_seconds: 1.0,
-                max_distance: Some(500.0),
-                scale: 2.0.into(),
-                // scale: ValueOverTime::Lerp(Lerp::new(2.0, 20.)),
-                bursts: vec![ParticleBurst::new(0.0, 25)],
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(transform.translation.x, transform.translation.y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing);
-}
-
-fn emit_ship_destruction_particles(
-    cmd: &mut Commands,
-    transform: &Transform,
-    particle_pixel_texture: &ParticlePixelTexture,
-) {
-    //TODO scale with ship's contained/accumulated energy, aka spirit energy aka prana aka chi
-    let n_particles = 1000;
-    let n_burst = 200;
-    let rate = 150.0;
-    let duration = 6.0;
-    cmd
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: n_particles,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: ValueOverTime::Lerp(Lerp::new(rate, 0.)),
-                initial_speed: JitteredValue::jittered(200.0, -100.0..0.0),
-                lifetime: JitteredValue::jittered(duration, (-duration * 0.3)..0.0),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::RED, 0.0),
-                    CurvePoint::new(Color::BLACK, 1.0),
-                ])),
-                emitter_shape: EmitterShape::CircleSegment(CircleSegment {
-                    radius: 10.0.into(),
-                    ..default()
-                }),
-                looping: false,
-                system_duration_seconds: duration,
-                max_distance: Some(1000.0),
-                scale: 3.0.into(),
-                bursts: vec![ParticleBurst::new(0.0, n_burst)],
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(transform.translation.x, transform.translation.y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing);
+    effects.spawn_effect(
+        "asteroid_clash",
+        Transform::from_xyz(collision_pt.x, collision_pt.y, 0.0),
+        EffectParams::default()
+            .with_direction(normalized.truncate().to_angle())
+            .with_intensity(intensity),
+    );
 }
@@ -15,7 +15,7 @@ pub struct CollisionPlayerShipEvent;
 #[derive(Event)]
 pub struct CollisionProjectileAsteroidEvent;
 
-#[derive(Default)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Avatars {
     PlayerShip,
     Asteroid,
@@ -2,7 +2,7 @@ use bevy::diagnostic::DiagnosticsStore;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 
-use crate::OnDebugDisplay;
+use crate::debug::OnDebugDisplay;
 
 /// Marker to find the container entity so we can show/hide the FPS counter
 #[derive(Component)]
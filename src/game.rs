@@ -1,17 +1,15 @@
-use bevy::render::camera::{ScalingMode, Viewport};
-use bevy_vector_shapes::painter::ShapePainter;
+use clap::ValueEnum;
 use lazy_static::lazy_static;
 
 use bevy::prelude::*;
-use bevy::time::Stopwatch;
 use bevy_rapier2d::prelude::*;
 
-use crate::audio::{
-    AsteroidClashSound, AsteroidDestroyedSound, ProjectileEmitSound, ProjectileImpactSound,
-    ShipDamagedSound, ShipThrustSound, ShipThrustSoundStopwatch, VesselDestroyedSound,
-};
+use crate::audio::EngineSounds;
 use crate::components::Score;
+use crate::music::{BackgroundMusic, MusicStem};
 use crate::play::play_plugin;
+use crate::replay::replay_plugin;
+use crate::rng::{GameRng, RngSeed};
 use crate::utils::Heading;
 
 // NEWTYPES
@@ -50,6 +48,7 @@ pub const DEFAULT_DAMAGE: i32 = 1;
 pub const DEFAULT_DURATION_SECS: u64 = 5;
 pub const DEFAULT_RESTITUTION: f32 = 0.5;
 pub const DEFAULT_THRUST_FORCE_MAGNITUDE: f32 = 10000.;
+pub const DEFAULT_RNG_SEED: u64 = 0;
 
 // UI
 pub const SCOREBOARD_FONT_SIZE: f32 = 20.0;
@@ -73,6 +72,8 @@ pub const SHIP_THRUST_FORCE_MAGNITUDE: f32 = 50000.; // dev
 pub const INIT_ASTEROID_MOVESPEED: Speed = 300.;
 pub const INIT_ASTEROID_DAMAGE: i32 = 1;
 pub const INIT_ASTEROID_RESTITUTION: f32 = 0.3;
+// score for destroying one
+pub const ASTEROID_POINTS: usize = 1;
 
 pub const SMALL_ASTEROID_R: f32 = 15.;
 pub const SMALL_ASTEROID_HEALTH: i32 = 1;
@@ -83,25 +84,39 @@ pub const MEDIUM_ASTEROID_HEALTH: i32 = 3;
 pub const LARGE_ASTEROID_R: f32 = 50.;
 pub const LARGE_ASTEROID_HEALTH: i32 = 5;
 
+// Asteroid field, the default play layout
+pub const FIELD_ASTEROID_COUNT: usize = 10;
+// no asteroid spawns this close to the ship
+pub const FIELD_CLEAR_RADIUS: f32 = 250.;
+pub const FIELD_MIN_ASTEROID_SPEED: Speed = 20.;
+pub const FIELD_MAX_ASTEROID_SPEED: Speed = 80.;
+
 // Projectile
 // any lower than 0.01 seems to have little effect (essential projectile vs med asteroid)
 pub const PROJECTILE_RESTITUTION: f32 = 0.01;
 pub const PROJECTILE_MASS: f32 = 10.;
 
-pub fn game_plugin(app: &mut App) {
-    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(2.))
+// Everything that affects gameplay state. Needs no window, renderer or audio, see headless.rs
+pub fn simulation_plugin(app: &mut App) {
+    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(2.).in_fixed_schedule())
         // .add_plugins(RapierDebugRenderPlugin::default())
         .insert_resource(Score(0))
+        .init_resource::<RngSeed>()
+        .init_resource::<GameRng>()
         .init_state::<GameState>()
-        .add_systems(Startup, (load_assets, setup_menu).chain())
-        .add_plugins(play_plugin);
+        .add_systems(Startup, configure_physics_timestep)
+        .add_plugins((play_plugin, replay_plugin));
 }
 
-pub fn setup_menu(
-    mut game_state: ResMut<NextState<GameState>>, // mut meshes: ResMut<Assets<Mesh>>,
-                                                  // mut materials: ResMut<Assets<ColorMaterial>>,
+// Step physics exactly once per FixedUpdate tick so the simulation never depends on frame timing
+pub fn configure_physics_timestep(
+    mut rapier_config: ResMut<RapierConfiguration>,
+    time: Res<Time<Fixed>>,
 ) {
-    game_state.set(GameState::Play);
+    rapier_config.timestep_mode = TimestepMode::Fixed {
+        dt: time.timestep().as_secs_f32(),
+        substeps: 1,
+    };
 }
 
 pub fn load_assets(
@@ -110,17 +125,7 @@ pub fn load_assets(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    // commands.spawn(Camera2dBundle::default());
-    let mut camera_bundle = Camera2dBundle::default();
-    camera_bundle.projection.scaling_mode = ScalingMode::FixedVertical(1080.);
-    // camera_bundle.projection.scaling_mode = ScalingMode::WindowSize(0.5);
-    // {
-    //     max_width: 1920.,
-    //     max_height: 1080.,
-    // };
-    let camera = cmd.spawn(camera_bundle);
-
-    cmd.insert_resource(ShipThrustSoundStopwatch(Stopwatch::new()));
+    // camera is spawned in display.rs
 
     // let handle_playership_mesh = meshes.add(Triangle2d::new(
     //     Vec2::new(-SHIP_HALF_WIDTH, -SHIP_HALF_WIDTH),
@@ -133,36 +138,20 @@ pub fn load_assets(
     // let handle_playership_colormaterial = materials.add(Color::LIME_GREEN);
     // commands.insert_resource(PlayerShipMaterialHandle(handle_playership_colormaterial));
 
-    // let background_music = asset_server.load("sounds/Windless Slopes.ogg");
-    // commands.insert_resource(BackgroundMusic(background_music));
-
-    // let moderate_thud_sound = asset_server.load("sounds/moderate_thud.wav");
-    // commands.insert_resource(SomeThudSound(moderate_thud_sound));
-
-    // v2.0 astral gameplay
-    // let destroy_soul_sound = asset_server.load("sounds/human_death.wav");
-    // commands.insert_resource(SoulDestroyedSound(destroy_soul_sound));
-
-    let light_shot_sound = asset_server.load("sounds/proj_core.wav");
-    cmd.insert_resource(ProjectileEmitSound(light_shot_sound));
-
-    let ship_thrust_sound = asset_server.load("sounds/thrust-med.wav");
-    cmd.insert_resource(ShipThrustSound(ship_thrust_sound));
-
-    let projectile_impact_sound = asset_server.load("sounds/scratch.wav");
-    cmd.insert_resource(ProjectileImpactSound(projectile_impact_sound));
-
-    let destroy_asteroid_sound = asset_server.load("sounds/destroy_asteroid.wav");
-    cmd.insert_resource(AsteroidDestroyedSound(destroy_asteroid_sound));
-
-    let damage_ship_sound = asset_server.load("sounds/damage_ship.wav");
-    cmd.insert_resource(ShipDamagedSound(damage_ship_sound));
-
-    let destroy_vessel_sound = asset_server.load("sounds/physical_death.wav");
-    cmd.insert_resource(VesselDestroyedSound(destroy_vessel_sound));
-
-    let asteroid_clash_sound = asset_server.load("sounds/asteroid_clash.wav");
-    cmd.insert_resource(AsteroidClashSound(asteroid_clash_sound));
+    // same track in the menu and in play, so it carries on instead of restarting. Play has no rhythm and
+    // intense stems yet, they go next to the ambient one as (MusicStem::Rhythm, handle) etc. Until then
+    // intensity is heard as the ambient stem swelling, see MusicStem::gain
+    let background_music: Handle<AudioSource> = asset_server.load("sounds/Windless Slopes.ogg");
+    let mut music = BackgroundMusic::default();
+    music.insert(GameState::Menu, vec![(MusicStem::Ambient, background_music.clone())]);
+    music.insert(GameState::Play, vec![(MusicStem::Ambient, background_music)]);
+    cmd.insert_resource(music);
+
+    // one-shot sounds are in the sound bank, see sound_bank.rs
+    cmd.insert_resource(EngineSounds {
+        lo: asset_server.load("sounds/thrust-lo.wav"),
+        hi: asset_server.load("sounds/thrust-hi.wav"),
+    });
 
     let handle_white_colormaterial = materials.add(Color::WHITE);
     cmd.insert_resource(WhiteMaterialHandle(handle_white_colormaterial));
@@ -180,35 +169,33 @@ pub fn load_assets(
     }
     cmd.insert_resource(AsteroidMeshHandles(asteroid_mesh_handles));
 
-    let playership_texture = asset_server.load("images/ship_K.png").into();
+    let playership_texture = asset_server.load("images/ship_K.png");
     cmd.insert_resource(PlayerShipTexture(playership_texture));
 
-    let particle_pixel_texture = asset_server.load("images/px.png").into();
-    cmd.insert_resource(ParticlePixelTexture(particle_pixel_texture));
-
-    let powerup_core_texture = asset_server.load("images/enemy_A.png").into();
+    let powerup_core_texture = asset_server.load("images/enemy_A.png");
     cmd.insert_resource(PowerupCoreTexture(powerup_core_texture));
-    let powerup_simple_texture = asset_server.load("images/enemy_C.png").into();
+    let powerup_simple_texture = asset_server.load("images/enemy_C.png");
     cmd.insert_resource(PowerupSimpleTexture(powerup_simple_texture));
-    let powerup_complex_texture = asset_server.load("images/enemy_E.png").into();
+    let powerup_complex_texture = asset_server.load("images/enemy_E.png");
     cmd.insert_resource(PowerupComplexTexture(powerup_complex_texture));
 
-    let star_core_texture = asset_server.load("images/star_06.png").into();
+    let star_core_texture = asset_server.load("images/star_06.png");
     cmd.insert_resource(StarCoreTexture(star_core_texture));
-    let star_simple_texture = asset_server.load("images/star_04.png").into();
+    let star_simple_texture = asset_server.load("images/star_04.png");
     cmd.insert_resource(StarSimpleTexture(star_simple_texture));
-    let star_complex_texture = asset_server.load("images/star_08.png").into();
+    let star_complex_texture = asset_server.load("images/star_08.png");
     cmd.insert_resource(StarComplexTexture(star_complex_texture));
 
-    let green_planet_texture = asset_server.load("images/planet00.png").into();
+    let green_planet_texture = asset_server.load("images/planet00.png");
     cmd.insert_resource(PlanetGreenTexture(green_planet_texture));
-    let grey_planet_texture = asset_server.load("images/planet04.png").into();
+    let grey_planet_texture = asset_server.load("images/planet04.png");
     cmd.insert_resource(PlanetGreyTexture(grey_planet_texture));
-    let purple_planet_texture = asset_server.load("images/planet09.png").into();
+    let purple_planet_texture = asset_server.load("images/planet09.png");
     cmd.insert_resource(PlanetPurpleTexture(purple_planet_texture));
 }
 
-#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+// starting state can be picked on the command line, see cli.rs
+#[derive(States, ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameState {
     Menu,
     #[default]
@@ -243,10 +230,7 @@ pub struct PlayerShipMaterialHandle(pub Handle<ColorMaterial>);
 #[derive(Resource, Deref)]
 pub struct WhiteMaterialHandle(pub Handle<ColorMaterial>);
 
-// Textures
-#[derive(Resource, Deref)]
-pub struct ParticlePixelTexture(pub Handle<Image>);
-
+// Textures, particle textures are in the effect presets
 #[derive(Resource, Deref)]
 pub struct PowerupCoreTexture(pub Handle<Image>);
 
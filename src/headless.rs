@@ -0,0 +1,70 @@
+use bevy::{app::PluginsState, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    game::{
+        simulation_plugin, AsteroidMaterialHandles, AsteroidMeshHandles, OnPlayScreen,
+        PlayerShipTexture, StarComplexTexture, StarCoreTexture, StarSimpleTexture,
+    },
+    rng::RngSeed,
+};
+
+// Headless simulation: runs the gameplay (simulation_plugin) on MinimalPlugins with no window, renderer or audio,
+// for integration tests and batch runs on machines without a GPU or display.
+//
+// The clock is driven manually: every app update advances time by exactly one fixed timestep, so one update
+// runs exactly one FixedUpdate tick regardless of how fast the host machine is.
+pub fn headless_app(seed: u64) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        // never pressed, keeps sample_keyboard_input running so replays can override its input
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(RngSeed(seed))
+        .add_systems(Startup, insert_placeholder_assets)
+        .add_systems(PostStartup, match_clock_to_timestep)
+        .add_plugins(simulation_plugin);
+    app
+}
+
+// The first update only runs startup and enters GameState::Play, after which every update is one fixed tick
+pub fn run_fixed_steps(app: &mut App, n_steps: u32) {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+        app.update();
+    }
+    for _ in 0..n_steps {
+        app.update();
+    }
+}
+
+// Empties the play screen of the default layout, for tests that set up their own bodies. Runs one tick so
+// physics drops the removed bodies before anything new is spawned
+pub fn clear_play_layout(app: &mut App) {
+    let layout: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<OnPlayScreen>>()
+        .iter(&app.world)
+        .collect();
+    for entity in layout {
+        app.world.entity_mut(entity).despawn_recursive();
+    }
+    run_fixed_steps(app, 1);
+}
+
+// Simulation systems only pass asset handles through to spawned bundles, so default handles suffice headless
+pub fn insert_placeholder_assets(mut cmd: Commands) {
+    cmd.insert_resource(AsteroidMeshHandles(vec![Handle::default(); 9]));
+    cmd.insert_resource(AsteroidMaterialHandles(vec![Handle::default()]));
+    cmd.insert_resource(PlayerShipTexture(Handle::default()));
+    cmd.insert_resource(StarCoreTexture(Handle::default()));
+    cmd.insert_resource(StarSimpleTexture(Handle::default()));
+    cmd.insert_resource(StarComplexTexture(Handle::default()));
+}
+
+// A replay may have changed the fixed timestep during startup
+fn match_clock_to_timestep(mut strategy: ResMut<TimeUpdateStrategy>, time: Res<Time<Fixed>>) {
+    *strategy = TimeUpdateStrategy::ManualDuration(time.timestep());
+}
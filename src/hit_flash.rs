@@ -0,0 +1,191 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::components::{AsteroidTag, Health, PlayerShipTag};
+
+// Damage feedback on the damaged entity itself: a short flash when its Health drops, and a darker
+// look the less health it has left. The ship's Sprite color is its own and is set directly. Asteroids
+// share their ColorMaterial, so instead of giving every asteroid its own material they swap between
+// shared variants of it, one per damage level plus one for the flash, made on first use.
+
+pub const HIT_FLASH_SECS: f32 = 0.12;
+pub const SHIP_FLASH_COLOR: Color = Color::rgb(1., 0.2, 0.2);
+pub const ASTEROID_FLASH_COLOR: Color = Color::WHITE;
+// how far toward the flash color asteroids go, they're flat grey so a full flash hides their shape
+pub const ASTEROID_FLASH_AMOUNT: f32 = 0.7;
+// brightness lost with all health gone
+pub const MAX_DAMAGE_DARKENING: f32 = 0.6;
+// darkening steps of asteroid materials
+pub const DAMAGE_LEVELS: u8 = 4;
+
+pub fn hit_flash_plugin(app: &mut App) {
+    app.init_resource::<DamageMaterials>().add_systems(
+        Update,
+        (track_health, (flash_ship_sprites, flash_asteroid_materials)).chain(),
+    );
+}
+
+// Health as of spawn and as of the last frame, and the flash left from the last drop
+#[derive(Component, Clone, Debug)]
+pub struct HitFlash {
+    pub max_health: i32,
+    pub last_health: i32,
+    pub flash: Timer,
+}
+
+impl HitFlash {
+    fn new(health: i32) -> Self {
+        let mut flash = Timer::from_seconds(HIT_FLASH_SECS, TimerMode::Once);
+        flash.tick(flash.duration());
+        Self {
+            max_health: health.max(1),
+            last_health: health,
+            flash,
+        }
+    }
+
+    // 0 at full health, 1 with none left
+    pub fn damage(&self) -> f32 {
+        (1. - self.last_health as f32 / self.max_health as f32).clamp(0., 1.)
+    }
+
+    fn is_flashing(&self) -> bool {
+        !self.flash.finished()
+    }
+
+    // 1 right after the hit, down to 0 when the flash is over
+    fn flash_amount(&self) -> f32 {
+        1. - self.flash.fraction()
+    }
+}
+
+// What the entity looked like before any damage
+#[derive(Component, Clone, Debug)]
+pub enum UndamagedLook {
+    Color(Color),
+    Material(Handle<ColorMaterial>),
+}
+
+// Shared damaged variants of asteroid materials
+#[derive(Resource, Default)]
+pub struct DamageMaterials {
+    variants: HashMap<(AssetId<ColorMaterial>, DamageVariant), Handle<ColorMaterial>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum DamageVariant {
+    Level(u8),
+    Flash,
+}
+
+impl DamageMaterials {
+    fn get(
+        &mut self,
+        base: &Handle<ColorMaterial>,
+        variant: DamageVariant,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        if variant == DamageVariant::Level(0) {
+            return base.clone();
+        }
+        if let Some(handle) = self.variants.get(&(base.id(), variant)) {
+            return handle.clone();
+        }
+        let Some(color) = materials.get(base).map(|material| material.color) else {
+            return base.clone();
+        };
+        let color = match variant {
+            DamageVariant::Level(level) => darkened(color, level as f32 / DAMAGE_LEVELS as f32),
+            DamageVariant::Flash => mix(color, ASTEROID_FLASH_COLOR, ASTEROID_FLASH_AMOUNT),
+        };
+        let handle = materials.add(color);
+        self.variants.insert((base.id(), variant), handle.clone());
+        handle
+    }
+}
+
+fn darkened(color: Color, damage: f32) -> Color {
+    let brightness = 1. - MAX_DAMAGE_DARKENING * damage;
+    Color::rgba(
+        color.r() * brightness,
+        color.g() * brightness,
+        color.b() * brightness,
+        color.a(),
+    )
+}
+
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    let mixed = from.lerp(to, amount.clamp(0., 1.));
+    Color::rgba(mixed.x, mixed.y, mixed.z, from.w)
+}
+
+// Starts tracking ships and asteroids, and flashes them when their health drops
+#[allow(clippy::type_complexity)]
+pub fn track_health(
+    mut cmd: Commands,
+    time: Res<Time>,
+    q_new_ships: Query<(Entity, &Health, &Sprite), (With<PlayerShipTag>, Without<HitFlash>)>,
+    q_new_asteroids: Query<
+        (Entity, &Health, &Handle<ColorMaterial>),
+        (With<AsteroidTag>, Without<HitFlash>),
+    >,
+    mut q_tracked: Query<(&Health, &mut HitFlash)>,
+) {
+    for (entity, health, sprite) in &q_new_ships {
+        cmd.entity(entity)
+            .insert((HitFlash::new(**health), UndamagedLook::Color(sprite.color)));
+    }
+    for (entity, health, material) in &q_new_asteroids {
+        cmd.entity(entity).insert((
+            HitFlash::new(**health),
+            UndamagedLook::Material(material.clone()),
+        ));
+    }
+    for (health, mut hit_flash) in &mut q_tracked {
+        hit_flash.flash.tick(time.delta());
+        if **health < hit_flash.last_health {
+            hit_flash.flash.reset();
+        }
+        if **health != hit_flash.last_health {
+            hit_flash.last_health = **health;
+        }
+    }
+}
+
+pub fn flash_ship_sprites(mut q_ships: Query<(&HitFlash, &UndamagedLook, &mut Sprite)>) {
+    for (hit_flash, look, mut sprite) in &mut q_ships {
+        let UndamagedLook::Color(color) = look else {
+            continue;
+        };
+        let mut target = darkened(*color, hit_flash.damage());
+        if hit_flash.is_flashing() {
+            target = mix(target, SHIP_FLASH_COLOR, hit_flash.flash_amount());
+        }
+        if sprite.color != target {
+            sprite.color = target;
+        }
+    }
+}
+
+pub fn flash_asteroid_materials(
+    mut damage_materials: ResMut<DamageMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut q_asteroids: Query<(&HitFlash, &UndamagedLook, &mut Handle<ColorMaterial>)>,
+) {
+    for (hit_flash, look, mut material) in &mut q_asteroids {
+        let UndamagedLook::Material(base) = look else {
+            continue;
+        };
+        let variant = match hit_flash.is_flashing() {
+            true => DamageVariant::Flash,
+            false => {
+                DamageVariant::Level((hit_flash.damage() * DAMAGE_LEVELS as f32).round() as u8)
+            }
+        };
+        let target = damage_materials.get(base, variant, &mut materials);
+        if *material != target {
+            *material = target;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_particle_systems::ParticleSystemPlugin;
+use bevy_vector_shapes::Shape2dPlugin;
+
+pub mod animation;
+pub mod archetypes;
+pub mod audio;
+pub mod avatars;
+pub mod camera_shake;
+pub mod cli;
+pub mod combat_text;
+pub mod components;
+pub mod controls;
+pub mod debug;
+pub mod display;
+pub mod effect_library;
+pub mod effects;
+pub mod events;
+pub mod fps;
+pub mod game;
+pub mod headless;
+pub mod hit_flash;
+pub mod menu;
+pub mod music;
+pub mod physics;
+pub mod play;
+pub mod projectile_looks;
+pub mod replay;
+pub mod rng;
+pub mod ron_asset;
+pub mod settings;
+pub mod ship_looks;
+pub mod sound_bank;
+pub mod trails;
+pub mod utils;
+
+pub const BACKGROUND_COLOR: Color = Color::rgb(0., 0., 0.);
+
+// The whole game on top of DefaultPlugins. Sub-plugins can be switched off, e.g. to run without sound
+// or the debug overlay. effects and audio only react to events emitted by play.
+// For gameplay without a window see headless::headless_app.
+pub struct AionPlugin {
+    pub play: bool,
+    pub effects: bool,
+    pub audio: bool,
+    pub debug: bool,
+}
+
+impl Default for AionPlugin {
+    fn default() -> Self {
+        Self {
+            play: true,
+            effects: true,
+            audio: true,
+            debug: true,
+        }
+    }
+}
+
+impl Plugin for AionPlugin {
+    fn build(&self, app: &mut App) {
+        // before anything else so settings are in place when the other plugins and load_assets run
+        app.add_plugins(settings::settings_plugin)
+            .insert_resource(ClearColor(BACKGROUND_COLOR))
+            .add_systems(Startup, game::load_assets)
+            .add_plugins(display::display_plugin);
+
+        if self.play {
+            app.add_plugins((game::simulation_plugin, menu::menu_plugin));
+        }
+        if self.effects || self.debug {
+            app.add_plugins(Shape2dPlugin::default());
+        }
+        if self.effects {
+            app.add_plugins((ParticleSystemPlugin, play::play_effects_plugin));
+        }
+        if self.audio {
+            app.add_plugins(audio::audio_plugin);
+        }
+        if self.debug {
+            app.add_plugins(debug::debug_plugin);
+        }
+    }
+}
@@ -1,220 +1,93 @@
-#![allow(unused)]
-
-use std::fmt::Debug;
-
-use bevy::{
-    diagnostic::FrameTimeDiagnosticsPlugin,
-    prelude::*,
-    window::{WindowMode, WindowResized},
+use std::process::ExitCode;
+
+use aion_bevy::{
+    cli::{LaunchArgs, DEFAULT_HEADLESS_STEPS},
+    components::Score,
+    game::GameState,
+    headless::{headless_app, run_fixed_steps},
+    replay::{default_replay_path, ReplayMode, ReplayPlayback},
+    rng::RngSeed,
+    settings::{default_settings_path, GameplaySettings, Settings, SettingsPath},
+    AionPlugin,
 };
-use bevy_particle_systems::ParticleSystemPlugin;
-use bevy_vector_shapes::{painter::ShapePainter, shapes::LinePainter, Shape2dPlugin};
-use fps::{fps_counter_showhide, fps_text_update_system, setup_fps_counter, FpsRoot};
-use game::{BOTTOM_WALL, LEFT_WALL, LOGICAL_HEIGHT, LOGICAL_WIDTH, RIGHT_WALL, TOP_WALL};
-
-mod archetypes;
-mod audio;
-mod avatars;
-mod components;
-mod controls;
-mod effects;
-mod events;
-mod fps;
-mod game;
-mod physics;
-mod play;
-mod utils;
-
-const BACKGROUND_COLOR: Color = Color::rgb(0., 0., 0.);
+use bevy::prelude::*;
+use clap::Parser;
+
+fn main() -> ExitCode {
+    let args = LaunchArgs::parse();
+    match args.headless {
+        true => run_headless(&args),
+        false => run_windowed(args),
+    }
+}
 
-fn main() {
+fn run_windowed(args: LaunchArgs) -> ExitCode {
+    let settings_path = args.config.clone().unwrap_or_else(default_settings_path);
+    let (settings, settings_load_log) = Settings::load_or_default(&settings_path);
+    let replay_mode = match (&args.replay, &args.record) {
+        (Some(path), _) => ReplayMode::Playback(path.clone()),
+        (None, Some(path)) => ReplayMode::Record(path.clone()),
+        (None, None) if settings.gameplay.record_replays => {
+            ReplayMode::Record(default_replay_path())
+        }
+        (None, None) => ReplayMode::Off,
+    };
+    // replays start right away instead of at the main menu
+    let start_state = match args.replay {
+        Some(_) => GameState::Play,
+        None => args.state,
+    };
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Aion v0.1.0".to_string(),
-                position: WindowPosition::Centered(MonitorSelection::Index(2)),
-                resolution: (LOGICAL_WIDTH, LOGICAL_HEIGHT).into(),
-                // mode: WindowMode::Fullscreen,
-                // resolution: (LOGICAL_WIDTH * 0.75, LOGICAL_HEIGHT * 0.75).into(),
-                ..default()
-            }),
+            primary_window: Some(args.window(&settings.video)),
             ..default()
         }))
-        .add_plugins(FrameTimeDiagnosticsPlugin::default())
-        .add_plugins(Shape2dPlugin::default())
-        .add_plugins(ParticleSystemPlugin)
-        .insert_resource(ClearColor(BACKGROUND_COLOR))
-        .insert_resource(ShowDebugDisplay(false))
-        .insert_resource(ResolutionSettings {
-            large: Vec2::new(1920., 1080.),
-            medium: Vec2::new(800., 600.),
-            small: Vec2::new(640., 360.),
-        })
-        .add_systems(Startup, (setup_fps_counter, setup_resolution_display))
-        .add_systems(
-            Update,
-            (
-                (
-                    bevy::window::close_on_esc,
-                    toggle_debug_display,
-                    update_debug_display_visibility,
-                    on_resize_system,
-                    toggle_resolution,
-                ),
-                (fps_text_update_system, fps_counter_showhide, draw_grid)
-                    .run_if(is_debug_display_on),
-            ),
-        )
-        .add_plugins(game::game_plugin)
+        .insert_state(start_state)
+        .insert_resource(RngSeed(args.seed))
+        .insert_resource(args.layout(&settings.gameplay))
+        .insert_resource(replay_mode)
+        .insert_resource(SettingsPath(settings_path))
+        .insert_resource(settings)
+        .insert_resource(settings_load_log)
+        .insert_resource(args)
+        .add_plugins(AionPlugin::default())
         .run();
+    ExitCode::SUCCESS
 }
 
-#[derive(Resource, Deref, DerefMut)]
-struct ShowDebugDisplay(bool);
-
-#[derive(Component)]
-struct OnDebugDisplay;
-
-#[derive(Component)]
-struct ResolutionText;
-
-#[derive(Resource)]
-struct ResolutionSettings {
-    large: Vec2,
-    medium: Vec2,
-    small: Vec2,
-}
-
-fn is_debug_display_on(show_debug_display: Res<ShowDebugDisplay>) -> bool {
-    **show_debug_display
-}
-
-fn toggle_debug_display(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut show_debug_display: ResMut<ShowDebugDisplay>,
-) {
-    if keys.just_pressed(KeyCode::Backquote) {
-        **show_debug_display = !**show_debug_display;
+// Fails when the replay can't be loaded or desyncs, so recorded runs can be checked in batch.
+// User settings are ignored so the outcome only depends on the arguments.
+fn run_headless(args: &LaunchArgs) -> ExitCode {
+    let mut app = headless_app(args.seed);
+    app.insert_resource(args.layout(&GameplaySettings::default()));
+    if let Some(path) = &args.replay {
+        app.insert_resource(ReplayMode::Playback(path.clone()));
     }
-}
+    run_fixed_steps(&mut app, 0);
 
-// TODO all use OnDebugDisplay,
-fn update_debug_display_visibility(
-    show_debug_display: Res<ShowDebugDisplay>,
-    mut q_debug_visibility: Query<&mut Visibility, With<OnDebugDisplay>>,
-    // mut q_fps: Query<&mut Visibility, (Without<Node>, Without<OnDebugDisplay>, With<FpsRoot>)>,
-) {
-    if show_debug_display.is_changed() {
-        for mut vis in &mut q_debug_visibility {
-            *vis = match **show_debug_display {
-                true => Visibility::Visible,
-                false => Visibility::Hidden,
-            };
+    if let Some(path) = &args.replay {
+        if *app.world.resource::<ReplayMode>() == ReplayMode::Off {
+            eprintln!("failed to load replay {:?}", path);
+            return ExitCode::FAILURE;
         }
-        // let mut fps_vis = q_fps.single_mut();
-        // *fps_vis = match **show_debug_display {
-        //     true => Visibility::Visible,
-        //     _ => Visibility::Hidden,
-        // };
-    }
-}
-
-fn setup_resolution_display(mut cmd: Commands) {
-    cmd.spawn((
-        NodeBundle {
-            background_color: BackgroundColor(Color::BLACK.with_a(0.5)),
-            z_index: ZIndex::Global(i32::MAX),
-            style: Style {
-                position_type: PositionType::Absolute,
-                right: Val::Percent(1.),
-                top: Val::Percent(3.),
-                bottom: Val::Auto,
-                left: Val::Auto,
-                padding: UiRect::all(Val::Px(4.0)),
-                ..default()
-            },
-            ..default()
-        },
-        OnDebugDisplay,
-    ))
-    .with_children(|root| {
-        root.spawn((
-            TextBundle::from_section(
-                "Resolution",
-                TextStyle {
-                    font_size: 20.0,
-                    color: Color::LIME_GREEN,
-                    ..default()
-                },
-            ),
-            ResolutionText,
-        ));
-    });
-}
-
-/// This system shows how to request the window to a new resolution
-fn toggle_resolution(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut windows: Query<&mut Window>,
-    resolution: Res<ResolutionSettings>,
-) {
-    let mut window = windows.single_mut();
-
-    if keys.just_pressed(KeyCode::Digit1) {
-        let res = resolution.small;
-        window.resolution.set(res.x, res.y);
     }
-    if keys.just_pressed(KeyCode::Digit2) {
-        let res = resolution.medium;
-        window.resolution.set(res.x, res.y);
-    }
-    if keys.just_pressed(KeyCode::Digit3) {
-        let res = resolution.large;
-        window.resolution.set(res.x, res.y);
-    }
-}
-
-/// This system shows how to respond to a window being resized.
-/// Whenever the window is resized, the text will update with the new resolution.
-fn on_resize_system(
-    mut q: Query<&mut Text, With<ResolutionText>>,
-    mut resize_reader: EventReader<WindowResized>,
-) {
-    let mut text = q.single_mut();
-    for e in resize_reader.read() {
-        // When resolution is being changed
-        text.sections[0].value = format!("{:.1} x {:.1}", e.width, e.height);
-    }
-}
-
-fn draw_grid(mut painter: ShapePainter) {
-    let height = TOP_WALL - BOTTOM_WALL;
-    let width = RIGHT_WALL - LEFT_WALL;
-    let line_color = Color::rgba(1., 1., 1., 0.025);
-
-    painter.thickness = 1.;
-    painter.color = line_color;
-
-    let s = 100;
-
-    for x in (0..(width / 2.0) as usize).step_by(s) {
-        painter.line(
-            Vec3::new(x as f32, -height / 2., 0.),
-            Vec3::new(x as f32, height / 2., 0.),
-        );
-        painter.line(
-            Vec3::new(-(x as f32), -height / 2., 0.),
-            Vec3::new(-(x as f32), height / 2., 0.),
-        );
-    }
-    for y in (0..(height / 2.0) as usize).step_by(s) {
-        painter.line(
-            Vec3::new(-width / 2., y as f32, 0.),
-            Vec3::new(width / 2., y as f32, 0.),
-        );
-        painter.line(
-            Vec3::new(-width / 2., -(y as f32), 0.),
-            Vec3::new(width / 2., -(y as f32), 0.),
-        );
+    let n_steps = match (args.steps, app.world.get_resource::<ReplayPlayback>()) {
+        (Some(n_steps), _) => n_steps,
+        (None, Some(playback)) => playback.replay.inputs.len() as u32,
+        (None, None) => DEFAULT_HEADLESS_STEPS,
+    };
+    run_fixed_steps(&mut app, n_steps);
+
+    println!(
+        "simulated {} ticks, score {}",
+        n_steps,
+        **app.world.resource::<Score>()
+    );
+    match app.world.get_resource::<ReplayPlayback>() {
+        Some(playback) if playback.is_desynced => {
+            eprintln!("replay desynced");
+            ExitCode::FAILURE
+        }
+        _ => ExitCode::SUCCESS,
     }
 }
@@ -0,0 +1,614 @@
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    ui::RelativeCursorPosition,
+    window::{PrimaryWindow, WindowMode},
+};
+
+use crate::{
+    controls::{FlightModel, ShipAction},
+    display::{
+        next_window_mode, set_window_mode, set_windowed_size, ResolutionSettings, WindowGeometry,
+    },
+    effects::MIN_PARTICLE_DENSITY,
+    game::{despawn_screen, GameState, LABEL_COLOR},
+    settings::Settings,
+};
+
+// Main menu, pause overlay and the options screen reachable from both.
+// Esc pauses play and backs out of the pause overlay and options. Options only edit the Settings
+// resource, settings.rs applies (live) and saves it. Window options act on the window directly.
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::rgb(0.1, 0.35, 0.1);
+const SLIDER_TRACK: Color = Color::rgb(0.1, 0.1, 0.1);
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const MENU_Z_INDEX: i32 = 100;
+// slider values snap to this, so dragging doesn't rewrite the settings file every pixel
+const SLIDER_STEP: f32 = 0.05;
+
+pub fn menu_plugin(app: &mut App) {
+    app.init_state::<MenuScreen>()
+        .init_resource::<PendingRebind>()
+        .add_systems(OnEnter(GameState::Menu), open_main_menu)
+        .add_systems(OnExit(GameState::Menu), close_menu)
+        .add_systems(OnEnter(MenuScreen::Disabled), unpause_time)
+        .add_systems(OnEnter(MenuScreen::Main), (unpause_time, setup_main_menu))
+        .add_systems(OnExit(MenuScreen::Main), despawn_screen::<OnMainMenuScreen>)
+        .add_systems(OnEnter(MenuScreen::Pause), (pause_time, setup_pause_menu))
+        .add_systems(OnExit(MenuScreen::Pause), despawn_screen::<OnPauseScreen>)
+        .add_systems(OnEnter(MenuScreen::Options), setup_options_menu)
+        .add_systems(
+            OnExit(MenuScreen::Options),
+            (despawn_screen::<OnOptionsScreen>, cancel_rebind),
+        )
+        .add_systems(
+            Update,
+            (
+                capture_rebind.run_if(in_state(MenuScreen::Options)),
+                handle_escape,
+                button_colors,
+                navigation_buttons,
+                (option_buttons, drag_sliders, update_option_texts)
+                    .chain()
+                    .run_if(in_state(MenuScreen::Options)),
+            )
+                .chain(),
+        );
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MenuScreen {
+    #[default]
+    Disabled,
+    Main,
+    Pause,
+    Options,
+}
+
+// Action waiting for a key press on the options screen
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PendingRebind(pub Option<ShipAction>);
+
+#[derive(Component)]
+pub struct OnMainMenuScreen;
+
+#[derive(Component)]
+pub struct OnPauseScreen;
+
+#[derive(Component)]
+pub struct OnOptionsScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuButton {
+    Play,
+    Resume,
+    Options,
+    Back,
+    MainMenu,
+    Quit,
+    // index into ResolutionSettings::presets
+    Resolution(usize),
+    WindowMode,
+    FlightModel,
+    ReducedEffects,
+    ScreenShake,
+    Rebind(ShipAction),
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slider {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    UiVolume,
+    ParticleDensity,
+}
+
+impl Slider {
+    fn label(self) -> &'static str {
+        match self {
+            Slider::MasterVolume => "Master volume",
+            Slider::MusicVolume => "Music volume",
+            Slider::SfxVolume => "Effects volume",
+            Slider::UiVolume => "Interface volume",
+            Slider::ParticleDensity => "Particle density",
+        }
+    }
+
+    fn min(self) -> f32 {
+        match self {
+            Slider::ParticleDensity => MIN_PARTICLE_DENSITY,
+            _ => 0.,
+        }
+    }
+
+    fn value(self, settings: &Settings) -> f32 {
+        match self {
+            Slider::MasterVolume => settings.audio.master,
+            Slider::MusicVolume => settings.audio.music,
+            Slider::SfxVolume => settings.audio.sfx,
+            Slider::UiVolume => settings.audio.ui,
+            Slider::ParticleDensity => settings.video.particle_density,
+        }
+    }
+
+    fn value_mut(self, settings: &mut Settings) -> &mut f32 {
+        match self {
+            Slider::MasterVolume => &mut settings.audio.master,
+            Slider::MusicVolume => &mut settings.audio.music,
+            Slider::SfxVolume => &mut settings.audio.sfx,
+            Slider::UiVolume => &mut settings.audio.ui,
+            Slider::ParticleDensity => &mut settings.video.particle_density,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SliderFill(pub Slider);
+
+// Text showing the current value of an option
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionText {
+    Slider(Slider),
+    WindowMode,
+    FlightModel,
+    ReducedEffects,
+    ScreenShake,
+    Key(ShipAction),
+}
+
+fn action_label(action: ShipAction) -> &'static str {
+    match action {
+        ShipAction::TurnLeft => "Turn left",
+        ShipAction::TurnRight => "Turn right",
+        ShipAction::Thrust => "Thrust",
+        ShipAction::Fire => "Fire",
+        ShipAction::Restart => "Restart",
+    }
+}
+
+fn on_off(value: bool) -> String {
+    match value {
+        true => "On".to_string(),
+        false => "Off".to_string(),
+    }
+}
+
+// Where Back and Esc lead from the options screen
+fn options_parent(game_state: &GameState) -> MenuScreen {
+    match game_state {
+        GameState::Play => MenuScreen::Pause,
+        _ => MenuScreen::Main,
+    }
+}
+
+pub fn open_main_menu(mut next_menu_screen: ResMut<NextState<MenuScreen>>) {
+    next_menu_screen.set(MenuScreen::Main);
+}
+
+pub fn close_menu(mut next_menu_screen: ResMut<NextState<MenuScreen>>) {
+    next_menu_screen.set(MenuScreen::Disabled);
+}
+
+// Gameplay runs in FixedUpdate off virtual time, so pausing it freezes the simulation (and replays)
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+pub fn handle_escape(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_state: Res<State<GameState>>,
+    menu_screen: Res<State<MenuScreen>>,
+    mut next_menu_screen: ResMut<NextState<MenuScreen>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    match (game_state.get(), menu_screen.get()) {
+        (GameState::Play, MenuScreen::Disabled) => next_menu_screen.set(MenuScreen::Pause),
+        (GameState::Play, MenuScreen::Pause) => next_menu_screen.set(MenuScreen::Disabled),
+        (game_state, MenuScreen::Options) => next_menu_screen.set(options_parent(game_state)),
+        _ => {}
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn button_colors(
+    mut q_buttons: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<MenuButton>)>,
+) {
+    for (interaction, mut color) in q_buttons.iter_mut() {
+        *color = match *interaction {
+            Interaction::Pressed => PRESSED_BUTTON.into(),
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        };
+    }
+}
+
+pub fn navigation_buttons(
+    q_buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_menu_screen: ResMut<NextState<MenuScreen>>,
+    mut evw_exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            MenuButton::Play => next_game_state.set(GameState::Play),
+            MenuButton::Resume => next_menu_screen.set(MenuScreen::Disabled),
+            MenuButton::Options => next_menu_screen.set(MenuScreen::Options),
+            MenuButton::Back => next_menu_screen.set(options_parent(game_state.get())),
+            MenuButton::MainMenu => next_game_state.set(GameState::Menu),
+            MenuButton::Quit => {
+                evw_exit.send(AppExit);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn option_buttons(
+    q_buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    resolution: Res<ResolutionSettings>,
+    geometry: Res<WindowGeometry>,
+    mut settings: ResMut<Settings>,
+    mut pending_rebind: ResMut<PendingRebind>,
+) {
+    for (interaction, button) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            MenuButton::Resolution(index) => {
+                if let Ok(mut window) = q_window.get_single_mut() {
+                    set_windowed_size(&mut window, resolution.presets()[index], &geometry);
+                }
+            }
+            MenuButton::WindowMode => {
+                if let Ok(mut window) = q_window.get_single_mut() {
+                    let mode = next_window_mode(window.mode);
+                    set_window_mode(&mut window, mode, &geometry);
+                }
+            }
+            MenuButton::FlightModel => {
+                settings.gameplay.flight_model = match settings.gameplay.flight_model {
+                    FlightModel::Assisted => FlightModel::Newtonian,
+                    FlightModel::Newtonian => FlightModel::Assisted,
+                };
+            }
+            MenuButton::ReducedEffects => {
+                settings.accessibility.reduced_effects = !settings.accessibility.reduced_effects;
+            }
+            MenuButton::ScreenShake => {
+                settings.accessibility.screen_shake = !settings.accessibility.screen_shake;
+            }
+            MenuButton::Rebind(action) => **pending_rebind = Some(action),
+            _ => {}
+        }
+    }
+}
+
+pub fn drag_sliders(
+    q_sliders: Query<(&Interaction, &RelativeCursorPosition, &Slider)>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, cursor, slider) in q_sliders.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+        let value = ((position.x.clamp(0., 1.) / SLIDER_STEP).round() * SLIDER_STEP).max(slider.min());
+        if slider.value(&settings) != value {
+            *slider.value_mut(&mut settings) = value;
+        }
+    }
+}
+
+// Binds the next key pressed to the pending action, Esc cancels
+pub fn capture_rebind(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut pending_rebind: ResMut<PendingRebind>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(action) = **pending_rebind else {
+        return;
+    };
+    let Some(key) = keys.get_just_pressed().next().copied() else {
+        return;
+    };
+    **pending_rebind = None;
+    if key == KeyCode::Escape {
+        // don't also back out of the options screen
+        keys.clear_just_pressed(KeyCode::Escape);
+        return;
+    }
+    let bindings = &mut settings.controls;
+    let previous_key = bindings.key(action);
+    // an action that already used the key takes over the old one, so no action is ever left unbound
+    for other in ShipAction::ALL {
+        if other != action && bindings.key(other) == key {
+            *bindings.key_mut(other) = previous_key;
+        }
+    }
+    *bindings.key_mut(action) = key;
+}
+
+pub fn cancel_rebind(mut pending_rebind: ResMut<PendingRebind>) {
+    **pending_rebind = None;
+}
+
+pub fn update_option_texts(
+    settings: Res<Settings>,
+    pending_rebind: Res<PendingRebind>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_texts: Query<(&mut Text, &OptionText)>,
+    mut q_fills: Query<(&mut Style, &SliderFill)>,
+) {
+    let window_mode = q_window.get_single().map(|window| window.mode).ok();
+    for (mut text, option) in q_texts.iter_mut() {
+        let value = match *option {
+            OptionText::Slider(slider) => format!("{:.0}%", slider.value(&settings) * 100.),
+            OptionText::WindowMode => match window_mode {
+                Some(WindowMode::Windowed) | None => "Windowed".to_string(),
+                Some(WindowMode::BorderlessFullscreen) => "Borderless fullscreen".to_string(),
+                Some(WindowMode::Fullscreen | WindowMode::SizedFullscreen) => {
+                    "Fullscreen".to_string()
+                }
+            },
+            OptionText::FlightModel => format!("{:?}", settings.gameplay.flight_model),
+            OptionText::ReducedEffects => on_off(settings.accessibility.reduced_effects),
+            OptionText::ScreenShake => on_off(settings.accessibility.screen_shake),
+            OptionText::Key(action) => match **pending_rebind {
+                Some(pending) if pending == action => "press a key".to_string(),
+                _ => format!("{:?}", settings.controls.key(action)),
+            },
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+    for (mut style, fill) in q_fills.iter_mut() {
+        let width = Val::Percent(fill.0.value(&settings) * 100.);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
+
+fn screen_root(background: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(10.),
+            ..default()
+        },
+        background_color: background.into(),
+        z_index: ZIndex::Global(MENU_Z_INDEX),
+        ..default()
+    }
+}
+
+fn text_style(font_size: f32, color: Color) -> TextStyle {
+    TextStyle {
+        font_size,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_title(parent: &mut ChildBuilder, title: &str) {
+    parent.spawn(
+        TextBundle::from_section(title, text_style(48., LABEL_COLOR)).with_style(Style {
+            margin: UiRect::bottom(Val::Px(20.)),
+            ..default()
+        }),
+    );
+}
+
+fn spawn_heading(parent: &mut ChildBuilder, heading: &str) {
+    parent.spawn(
+        TextBundle::from_section(heading, text_style(26., LABEL_COLOR)).with_style(Style {
+            margin: UiRect::top(Val::Px(12.)),
+            ..default()
+        }),
+    );
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, button: MenuButton, width: f32) {
+    parent
+        .spawn((button_bundle(width), button))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style(22., TEXT_COLOR)));
+        });
+}
+
+// Button whose label shows the current value of an option
+fn spawn_option_button(parent: &mut ChildBuilder, button: MenuButton, option: OptionText) {
+    parent
+        .spawn((button_bundle(260.), button))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", text_style(22., TEXT_COLOR)),
+                option,
+            ));
+        });
+}
+
+fn button_bundle(width: f32) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            width: Val::Px(width),
+            height: Val::Px(40.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: NORMAL_BUTTON.into(),
+        ..default()
+    }
+}
+
+// Label on the left, controls on the right
+fn spawn_row(parent: &mut ChildBuilder, label: &str, spawn_controls: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(label, text_style(20., TEXT_COLOR)).with_style(Style {
+                    width: Val::Px(200.),
+                    ..default()
+                }),
+            );
+            spawn_controls(parent);
+        });
+}
+
+fn spawn_slider(parent: &mut ChildBuilder, slider: Slider, settings: &Settings) {
+    spawn_row(parent, slider.label(), |parent| {
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(260.),
+                        height: Val::Px(16.),
+                        ..default()
+                    },
+                    background_color: SLIDER_TRACK.into(),
+                    ..default()
+                },
+                RelativeCursorPosition::default(),
+                slider,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(slider.value(settings) * 100.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        background_color: LABEL_COLOR.into(),
+                        ..default()
+                    },
+                    SliderFill(slider),
+                ));
+            });
+        parent.spawn((
+            TextBundle::from_section("", text_style(20., TEXT_COLOR)).with_style(Style {
+                width: Val::Px(60.),
+                ..default()
+            }),
+            OptionText::Slider(slider),
+        ));
+    });
+}
+
+pub fn setup_main_menu(mut cmd: Commands) {
+    cmd.spawn((screen_root(Color::BLACK.with_a(0.8)), OnMainMenuScreen))
+        .with_children(|parent| {
+            spawn_title(parent, "AION");
+            spawn_button(parent, "Play", MenuButton::Play, 260.);
+            spawn_button(parent, "Options", MenuButton::Options, 260.);
+            spawn_button(parent, "Quit", MenuButton::Quit, 260.);
+        });
+}
+
+pub fn setup_pause_menu(mut cmd: Commands) {
+    cmd.spawn((screen_root(Color::BLACK.with_a(0.6)), OnPauseScreen))
+        .with_children(|parent| {
+            spawn_title(parent, "Paused");
+            spawn_button(parent, "Resume", MenuButton::Resume, 260.);
+            spawn_button(parent, "Options", MenuButton::Options, 260.);
+            spawn_button(parent, "Main menu", MenuButton::MainMenu, 260.);
+        });
+}
+
+pub fn setup_options_menu(
+    mut cmd: Commands,
+    settings: Res<Settings>,
+    resolution: Res<ResolutionSettings>,
+) {
+    cmd.spawn((screen_root(Color::BLACK.with_a(0.85)), OnOptionsScreen))
+        .with_children(|parent| {
+            spawn_title(parent, "Options");
+
+            spawn_heading(parent, "Audio");
+            for slider in [
+                Slider::MasterVolume,
+                Slider::MusicVolume,
+                Slider::SfxVolume,
+                Slider::UiVolume,
+            ] {
+                spawn_slider(parent, slider, &settings);
+            }
+
+            spawn_heading(parent, "Video");
+            spawn_row(parent, "Window size", |parent| {
+                for (index, size) in resolution.presets().iter().enumerate() {
+                    let label = format!("{}x{}", size.x, size.y);
+                    spawn_button(parent, &label, MenuButton::Resolution(index), 130.);
+                }
+            });
+            spawn_row(parent, "Display", |parent| {
+                spawn_option_button(parent, MenuButton::WindowMode, OptionText::WindowMode);
+            });
+            spawn_slider(parent, Slider::ParticleDensity, &settings);
+
+            spawn_heading(parent, "Gameplay");
+            spawn_row(parent, "Flight model", |parent| {
+                spawn_option_button(parent, MenuButton::FlightModel, OptionText::FlightModel);
+            });
+
+            spawn_heading(parent, "Accessibility");
+            spawn_row(parent, "Screen shake", |parent| {
+                spawn_option_button(parent, MenuButton::ScreenShake, OptionText::ScreenShake);
+            });
+            spawn_row(parent, "Reduced effects", |parent| {
+                spawn_option_button(parent, MenuButton::ReducedEffects, OptionText::ReducedEffects);
+            });
+
+            spawn_heading(parent, "Controls");
+            for action in ShipAction::ALL {
+                spawn_row(parent, action_label(action), |parent| {
+                    spawn_option_button(parent, MenuButton::Rebind(action), OptionText::Key(action));
+                });
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(20.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "Back", MenuButton::Back, 260.);
+                });
+        });
+}
@@ -0,0 +1,240 @@
+use bevy::{audio::Volume, prelude::*, utils::HashMap};
+
+use crate::{
+    audio::{AudioBus, AudioMixer, SoundPlayer},
+    components::{AsteroidTag, Health, PlayerShipTag},
+    effects::DestructionEffectEvent,
+    events::Avatars,
+    game::{GameState, INIT_SHIP_HEALTH},
+    sound_bank::Sound,
+};
+
+// time for one music track to fade out and the next to fade in, in real time so pausing doesn't stall it
+pub const MUSIC_CROSSFADE_SECS: f32 = 2.;
+// asteroids closer than this to the ship count as nearby, NEARBY_ASTEROIDS_FOR_FULL_INTENSITY of them
+// max out their share of the intensity
+pub const INTENSITY_NEARBY_RADIUS: f32 = 400.;
+pub const NEARBY_ASTEROIDS_FOR_FULL_INTENSITY: f32 = 4.;
+// added per destruction, halves every INTENSITY_DESTRUCTION_HALF_LIFE_SECS
+pub const INTENSITY_PER_DESTRUCTION: f32 = 0.25;
+pub const INTENSITY_DESTRUCTION_HALF_LIFE_SECS: f32 = 4.;
+// intensity per second the music follows with, rising faster than it calms down
+pub const INTENSITY_RISE_RATE: f32 = 0.5;
+pub const INTENSITY_FALL_RATE: f32 = 0.1;
+// the ambient stem plays this much quieter when nothing's going on and swells to full volume as intensity
+// rises, so intensity is heard even with ambient as the only stem
+pub const AMBIENT_CALM_GAIN: f32 = 0.6;
+
+// Music for each game state, crossfaded on state changes. A state plays one or more stems that start
+// together and stay in sync: the ambient stem always plays and swells with the MusicIntensity of play,
+// rhythm and intense stems are mixed in by it. Stingers play over the music on ship destruction and when the field is cleared.
+pub fn music_plugin(app: &mut App) {
+    app.init_resource::<MusicIntensity>().add_systems(
+        Update,
+        (
+            switch_music.run_if(state_changed::<GameState>),
+            update_music_intensity,
+            crossfade_music,
+            play_stingers,
+        )
+            .chain(),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MusicStem {
+    Ambient,
+    Rhythm,
+    Intense,
+}
+
+impl MusicStem {
+    // Volume of the stem at an intensity, each layer comes in over its own range
+    pub fn gain(self, intensity: f32) -> f32 {
+        match self {
+            MusicStem::Ambient => {
+                AMBIENT_CALM_GAIN + (1. - AMBIENT_CALM_GAIN) * smoothstep(0., 0.5, intensity)
+            }
+            MusicStem::Rhythm => smoothstep(0.25, 0.55, intensity),
+            MusicStem::Intense => smoothstep(0.6, 0.9, intensity),
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+// Stems for each game state, states without an entry are silent
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct BackgroundMusic(pub HashMap<GameState, Vec<(MusicStem, Handle<AudioSource>)>>);
+
+// Looping music stem, faded in and out by crossfade_music
+#[derive(Component, Clone, Debug)]
+pub struct MusicTrack {
+    pub stem: MusicStem,
+    pub source: Handle<AudioSource>,
+    // 0 silent ..= 1 full music bus volume
+    pub fade: f32,
+    pub is_fading_out: bool,
+}
+
+// How much is going on around the ship, 0 calm ..= 1 chaos. There are no enemies yet, so it's made of
+// nearby asteroids, damage taken and recent destruction
+#[derive(Resource, Default, Debug)]
+pub struct MusicIntensity {
+    // what the music currently plays at, eased towards target
+    pub level: f32,
+    pub target: f32,
+    pub recent_destruction: f32,
+}
+
+// Fades out whatever plays and fades in the new state's stems. When the new state plays exactly the same
+// stems they carry on, otherwise all of them start over so they stay in sync
+pub fn switch_music(
+    mut cmd: Commands,
+    state: Res<State<GameState>>,
+    music: Res<BackgroundMusic>,
+    mut q_tracks: Query<&mut MusicTrack>,
+) {
+    let next = music
+        .get(state.get())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let playing: Vec<_> = q_tracks
+        .iter()
+        .filter(|track| !track.is_fading_out)
+        .map(|track| (track.stem, track.source.clone()))
+        .collect();
+    let is_same = playing.len() == next.len() && next.iter().all(|stem| playing.contains(stem));
+    if is_same {
+        return;
+    }
+    for mut track in &mut q_tracks {
+        track.is_fading_out = true;
+    }
+    for (stem, source) in next {
+        cmd.spawn((
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::ZERO),
+            },
+            MusicTrack {
+                stem: *stem,
+                source: source.clone(),
+                fade: 0.,
+                is_fading_out: false,
+            },
+        ));
+    }
+}
+
+pub fn update_music_intensity(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut evr_destruction_effects: EventReader<DestructionEffectEvent>,
+    q_ship: Query<(&Transform, &Health), With<PlayerShipTag>>,
+    q_asteroids: Query<&Transform, With<AsteroidTag>>,
+    mut intensity: ResMut<MusicIntensity>,
+) {
+    let dt = time.delta_seconds();
+    let n_destroyed = evr_destruction_effects.read().count() as f32;
+    intensity.recent_destruction = (intensity.recent_destruction
+        * 0.5_f32.powf(dt / INTENSITY_DESTRUCTION_HALF_LIFE_SECS)
+        + n_destroyed * INTENSITY_PER_DESTRUCTION)
+        .min(1.);
+
+    intensity.target = match (state.get(), q_ship.get_single()) {
+        (GameState::Play, Ok((ship_transform, health))) => {
+            let n_nearby = q_asteroids
+                .iter()
+                .filter(|transform| {
+                    transform.translation.distance(ship_transform.translation)
+                        < INTENSITY_NEARBY_RADIUS
+                })
+                .count() as f32;
+            let nearby = (n_nearby / NEARBY_ASTEROIDS_FOR_FULL_INTENSITY).min(1.);
+            let damage = 1. - (**health as f32 / INIT_SHIP_HEALTH as f32).clamp(0., 1.);
+            (0.4 * nearby + 0.3 * damage + 0.3 * intensity.recent_destruction).clamp(0., 1.)
+        }
+        _ => 0.,
+    };
+
+    let rate = match intensity.target > intensity.level {
+        true => INTENSITY_RISE_RATE,
+        false => INTENSITY_FALL_RATE,
+    };
+    let step = rate * dt;
+    intensity.level += (intensity.target - intensity.level).clamp(-step, step);
+}
+
+pub fn crossfade_music(
+    mut cmd: Commands,
+    time: Res<Time<Real>>,
+    mixer: Res<AudioMixer>,
+    intensity: Res<MusicIntensity>,
+    mut q_tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+) {
+    let step = time.delta_seconds() / MUSIC_CROSSFADE_SECS;
+    for (entity, mut track, sink) in &mut q_tracks {
+        track.fade = match track.is_fading_out {
+            true => (track.fade - step).max(0.),
+            false => (track.fade + step).min(1.),
+        };
+        if track.is_fading_out && track.fade <= 0. {
+            // dropping the sink stops playback
+            cmd.entity(entity).despawn();
+            continue;
+        }
+        // the sink only shows up once the track is loaded
+        if let Some(sink) = sink {
+            let gain = track.stem.gain(intensity.level);
+            sink.set_volume(track.fade * gain * mixer.gain(AudioBus::Music));
+        }
+    }
+}
+
+// The field is cleared when the last asteroid goes, asteroids are despawned in the same tick as
+// their destruction event
+pub fn play_stingers(
+    mut evr_destruction_effects: EventReader<DestructionEffectEvent>,
+    q_asteroids: Query<(), With<AsteroidTag>>,
+    mut sounds: SoundPlayer,
+) {
+    let mut is_asteroid_destroyed = false;
+    for event in evr_destruction_effects.read() {
+        match event.avatar {
+            Avatars::PlayerShip => {
+                sounds.play(Sound::StingerShipDestroyed);
+            }
+            Avatars::Asteroid => is_asteroid_destroyed = true,
+            _ => {}
+        }
+    }
+    if is_asteroid_destroyed && q_asteroids.is_empty() {
+        sounds.play(Sound::StingerFieldCleared);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gains_follow_intensity() {
+        let levels = [0., 0.25, 0.5, 0.75, 1.];
+        for stem in [MusicStem::Ambient, MusicStem::Rhythm, MusicStem::Intense] {
+            let gains: Vec<f32> = levels.iter().map(|&level| stem.gain(level)).collect();
+            assert!(
+                gains.windows(2).all(|pair| pair[0] <= pair[1]),
+                "{stem:?} {gains:?}"
+            );
+            assert!(gains[0] < gains[4], "{stem:?} {gains:?}");
+            assert_eq!(gains[4], 1.);
+        }
+        assert_eq!(MusicStem::Ambient.gain(0.), AMBIENT_CALM_GAIN);
+        assert_eq!(MusicStem::Rhythm.gain(0.), 0.);
+        assert_eq!(MusicStem::Intense.gain(0.5), 0.);
+    }
+}
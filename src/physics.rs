@@ -4,14 +4,30 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
+    combat_text::{CombatText, CombatTextEvent},
     components::{
         AsteroidTag, CollisionRadius, Damage, DespawnDelay, Health, PlayerShipTag, ProjectileTag,
         Score,
     },
-    effects::{CollisionEffectEvent, DestructionEffectEvent},
+    effects::{CollisionEffectEvent, DestructionEffectEvent, Impact},
     events::Avatars,
+    game::ASTEROID_POINTS,
+    hit_flash::UndamagedLook,
 };
 
+// Gameplay bodies only collide with each other and effect debris only with other debris. Debris only
+// exists with effects on, it must never change the outcome of a game or replays stop being deterministic
+pub const GAMEPLAY_GROUP: Group = Group::GROUP_1;
+pub const DEBRIS_GROUP: Group = Group::GROUP_2;
+
+pub fn gameplay_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(GAMEPLAY_GROUP, GAMEPLAY_GROUP)
+}
+
+pub fn debris_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(DEBRIS_GROUP, DEBRIS_GROUP)
+}
+
 // TODO
 // - SOLUTION: fixedupdate will emit effect events, data flows to Update systems, which handles perceivable effects
 //   - thus is more like option A
@@ -19,11 +35,13 @@ use crate::{
 // destroy_ship_sound: Res<ShipDestroyedSound>,
 // destroy_asteroid_sound: Res<AsteroidDestroyedSound>,
 // asteroid_clash_sound: Res<AsteroidClashSound>,
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn handle_collisions(
     mut cmd: Commands,
     mut evr_collisions: EventReader<CollisionEvent>,
     mut evw_effects_collisions: EventWriter<CollisionEffectEvent>,
     mut evw_effects_destruction: EventWriter<DestructionEffectEvent>,
+    mut evw_combat_text: EventWriter<CombatTextEvent>,
     mut score: ResMut<Score>,
     q_proj: Query<
         (Entity, &Damage, &Transform, &Velocity),
@@ -49,133 +67,242 @@ pub fn handle_collisions(
             Without<ProjectileTag>,
         ),
     >,
+    // only passed on to effects
+    q_aster_looks: Query<
+        (
+            &Velocity,
+            &CollisionRadius,
+            Option<&Handle<ColorMaterial>>,
+            Option<&UndamagedLook>,
+        ),
+        With<AsteroidTag>,
+    >,
+    q_bodies: Query<(&Velocity, Option<&ReadMassProperties>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
 ) {
     for event in evr_collisions.read() {
-        match event {
-            CollisionEvent::Started(ent_a, ent_b, _flags) => {
-                let proj_a = q_proj.get(*ent_a).ok();
-                let proj_b = q_proj.get(*ent_b).ok();
-                let any_proj = proj_a.or(proj_b);
-
-                let aster_a = q_aster.get(*ent_a).is_ok();
-                let aster_b = q_aster.get(*ent_b).is_ok();
-                let is_any_aster = aster_a || aster_b;
-                let is_all_aster = aster_a && aster_b;
-
-                let ship_a = q_ship.get(*ent_a).is_ok();
-                let ship_b = q_ship.get(*ent_b).is_ok();
-                let is_any_ship = ship_a || ship_b;
-
-                // PROJ Collision Effect ONLY (not incl damage)
-                if let Some((id, _damage, transform, velocity)) = any_proj {
-                    evw_effects_collisions.send(CollisionEffectEvent {
-                        avatar_a: Avatars::Projectile,
-                        ent_a: Some(id),
-                        transform_a: Some(*transform),
-                        velocity_a: Some(*velocity),
-                        collision_radius_a: None,
-                        ..default()
-                    });
-                    cmd.entity(id).insert(DespawnDelay(Timer::new(
-                        Duration::from_secs_f32(2.0),
-                        TimerMode::Once,
-                    )));
-                }
+        let CollisionEvent::Started(ent_a, ent_b, _flags) = event else {
+            continue;
+        };
+        let impact = impact(
+            *ent_a,
+            *ent_b,
+            &q_bodies,
+            &rapier_context,
+            time.delta_seconds(),
+        );
 
-                // PROJ-ASTER
-                // No asteroid sound, simply projectile collision effects as above
-
-                if is_any_aster && any_proj.is_some() {
-                    let (aster_id, mut aster_health, _, aster_transform, _) = if aster_a {
-                        q_aster.get_mut(*ent_a).unwrap()
-                    } else {
-                        q_aster.get_mut(*ent_b).unwrap()
-                    };
-                    let (_proj_id, proj_dmg, _proj_transform, _proj_velocity) = any_proj.unwrap();
-
-                    **aster_health -= **proj_dmg;
-
-                    if **aster_health <= 0 {
-                        **score += 1;
-                        evw_effects_destruction.send(DestructionEffectEvent {
-                            avatar: Avatars::Asteroid,
-                            transform: *aster_transform,
-                        });
-                        cmd.entity(aster_id).despawn_recursive();
-                    }
-                }
+        let proj_a = q_proj.get(*ent_a).ok();
+        let proj_b = q_proj.get(*ent_b).ok();
+        let any_proj = proj_a.or(proj_b);
 
-                // PROJ-SHIP
-                if is_any_ship && any_proj.is_some() {
-                    // (Entity, &mut Health, &Transform),
-                    let (ship_id, mut ship_health, ship_transform) = if ship_a {
-                        q_ship.get_mut(*ent_a).unwrap()
-                    } else {
-                        q_ship.get_mut(*ent_b).unwrap()
-                    };
-                    let (_proj_id, proj_dmg, _proj_transform, _proj_velocity) = any_proj.unwrap();
-
-                    **ship_health -= **proj_dmg;
-
-                    if **ship_health <= 0 {
-                        evw_effects_destruction.send(DestructionEffectEvent {
-                            avatar: Avatars::PlayerShip,
-                            transform: *ship_transform,
-                        });
-                        cmd.entity(ship_id).despawn_recursive();
-                    } else {
-                        evw_effects_collisions.send(CollisionEffectEvent {
-                            avatar_a: Avatars::PlayerShip,
-                            transform_a: Some(*ship_transform),
-                            ..default()
-                        });
-                    }
-                }
+        let aster_a = q_aster.get(*ent_a).is_ok();
+        let aster_b = q_aster.get(*ent_b).is_ok();
+        let is_any_aster = aster_a || aster_b;
+        let is_all_aster = aster_a && aster_b;
 
-                // ASTER-ASTER
-                if is_all_aster {
-                    let (_, _, _, aster_a_transform, collision_radius_a) =
-                        q_aster.get(*ent_a).unwrap();
-                    let (_, _, _, aster_b_transform, _) = q_aster.get(*ent_b).unwrap();
-                    evw_effects_collisions.send(CollisionEffectEvent {
-                        avatar_a: Avatars::Asteroid,
-                        transform_a: Some(*aster_a_transform),
-                        collision_radius_a: Some(*collision_radius_a),
-                        avatar_b: Some(Avatars::Asteroid),
-                        transform_b: Some(*aster_b_transform),
-                        ..default()
-                    });
-                }
+        let ship_a = q_ship.get(*ent_a).is_ok();
+        let ship_b = q_ship.get(*ent_b).is_ok();
+        let is_any_ship = ship_a || ship_b;
 
-                // ASTER-SHIP
-                if is_any_ship && is_any_aster {
-                    let any_aster = q_aster.get(*ent_a).ok().or(q_aster.get(*ent_b).ok());
-
-                    let (ship_id, mut ship_health, ship_transform) = if ship_a {
-                        q_ship.get_mut(*ent_a).unwrap()
-                    } else {
-                        q_ship.get_mut(*ent_b).unwrap()
-                    };
-                    let (_aster_id, _, aster_dmg, _, _) = any_aster.unwrap();
-
-                    **ship_health -= **aster_dmg;
-
-                    if **ship_health <= 0 {
-                        evw_effects_destruction.send(DestructionEffectEvent {
-                            avatar: Avatars::PlayerShip,
-                            transform: *ship_transform,
-                        });
-                        cmd.entity(ship_id).despawn_recursive();
-                    } else {
-                        evw_effects_collisions.send(CollisionEffectEvent {
-                            avatar_a: Avatars::PlayerShip,
-                            transform_a: Some(*ship_transform),
-                            ..default()
-                        });
-                    }
-                }
+        // PROJ Collision Effect ONLY (not incl damage)
+        if let Some((id, _damage, transform, velocity)) = any_proj {
+            let avatar_b = match (is_any_aster, is_any_ship) {
+                (true, _) => Some(Avatars::Asteroid),
+                (_, true) => Some(Avatars::PlayerShip),
+                _ => None,
+            };
+            evw_effects_collisions.send(CollisionEffectEvent {
+                avatar_a: Avatars::Projectile,
+                ent_a: Some(id),
+                transform_a: Some(*transform),
+                velocity_a: Some(*velocity),
+                collision_radius_a: None,
+                avatar_b,
+                impact,
+                ..default()
+            });
+            cmd.entity(id).insert(DespawnDelay(Timer::new(
+                Duration::from_secs_f32(2.0),
+                TimerMode::Once,
+            )));
+        }
+
+        // PROJ-ASTER
+        // No asteroid sound, simply projectile collision effects as above
+
+        if let (true, Some((_proj_id, proj_dmg, _proj_transform, _proj_velocity))) =
+            (is_any_aster, any_proj)
+        {
+            let (aster_id, mut aster_health, _, aster_transform, _) = if aster_a {
+                q_aster.get_mut(*ent_a).unwrap()
+            } else {
+                q_aster.get_mut(*ent_b).unwrap()
+            };
+
+            // several hits can land in one tick, only the one that takes health to 0 destroys
+            let was_alive = **aster_health > 0;
+            **aster_health -= **proj_dmg;
+            if was_alive {
+                evw_combat_text.send(CombatTextEvent {
+                    position: aster_transform.translation,
+                    text: CombatText::Damage(**proj_dmg),
+                });
+            }
+
+            if was_alive && **aster_health <= 0 {
+                **score += ASTEROID_POINTS;
+                evw_combat_text.send(CombatTextEvent {
+                    position: aster_transform.translation,
+                    text: CombatText::Points(ASTEROID_POINTS),
+                });
+                let (aster_velocity, aster_radius, aster_material, undamaged_look) =
+                    q_aster_looks.get(aster_id).unwrap();
+                // earlier hits have swapped the material for a damaged or flashing variant
+                let material = match undamaged_look {
+                    Some(UndamagedLook::Material(base)) => Some(base.clone()),
+                    _ => aster_material.cloned(),
+                };
+                evw_effects_destruction.send(DestructionEffectEvent {
+                    avatar: Avatars::Asteroid,
+                    transform: *aster_transform,
+                    velocity: Some(*aster_velocity),
+                    collision_radius: Some(*aster_radius),
+                    material,
+                });
+                cmd.entity(aster_id).despawn_recursive();
+            }
+        }
+
+        // PROJ-SHIP
+        if let (true, Some((_proj_id, proj_dmg, _proj_transform, _proj_velocity))) =
+            (is_any_ship, any_proj)
+        {
+            // (Entity, &mut Health, &Transform),
+            let (ship_id, mut ship_health, ship_transform) = if ship_a {
+                q_ship.get_mut(*ent_a).unwrap()
+            } else {
+                q_ship.get_mut(*ent_b).unwrap()
+            };
+
+            let was_alive = **ship_health > 0;
+            **ship_health -= **proj_dmg;
+            if was_alive {
+                evw_combat_text.send(CombatTextEvent {
+                    position: ship_transform.translation,
+                    text: CombatText::Damage(**proj_dmg),
+                });
+            }
+
+            if was_alive && **ship_health <= 0 {
+                evw_effects_destruction.send(DestructionEffectEvent {
+                    avatar: Avatars::PlayerShip,
+                    transform: *ship_transform,
+                    velocity: q_bodies.get(ship_id).ok().map(|(velocity, _)| *velocity),
+                    ..default()
+                });
+                cmd.entity(ship_id).despawn_recursive();
+            } else if **ship_health > 0 {
+                evw_effects_collisions.send(CollisionEffectEvent {
+                    avatar_a: Avatars::PlayerShip,
+                    transform_a: Some(*ship_transform),
+                    impact,
+                    ..default()
+                });
+            }
+        }
+
+        // ASTER-ASTER
+        if is_all_aster {
+            let (_, _, _, aster_a_transform, collision_radius_a) = q_aster.get(*ent_a).unwrap();
+            let (_, _, _, aster_b_transform, _) = q_aster.get(*ent_b).unwrap();
+            evw_effects_collisions.send(CollisionEffectEvent {
+                avatar_a: Avatars::Asteroid,
+                transform_a: Some(*aster_a_transform),
+                collision_radius_a: Some(*collision_radius_a),
+                avatar_b: Some(Avatars::Asteroid),
+                transform_b: Some(*aster_b_transform),
+                impact,
+                ..default()
+            });
+        }
+
+        // ASTER-SHIP
+        if is_any_ship && is_any_aster {
+            let any_aster = q_aster.get(*ent_a).ok().or(q_aster.get(*ent_b).ok());
+
+            let (ship_id, mut ship_health, ship_transform) = if ship_a {
+                q_ship.get_mut(*ent_a).unwrap()
+            } else {
+                q_ship.get_mut(*ent_b).unwrap()
+            };
+            let (_aster_id, _, aster_dmg, _, _) = any_aster.unwrap();
+
+            let was_alive = **ship_health > 0;
+            **ship_health -= **aster_dmg;
+            if was_alive {
+                evw_combat_text.send(CombatTextEvent {
+                    position: ship_transform.translation,
+                    text: CombatText::Damage(**aster_dmg),
+                });
             }
-            _ => {}
+
+            if was_alive && **ship_health <= 0 {
+                evw_effects_destruction.send(DestructionEffectEvent {
+                    avatar: Avatars::PlayerShip,
+                    transform: *ship_transform,
+                    velocity: q_bodies.get(ship_id).ok().map(|(velocity, _)| *velocity),
+                    ..default()
+                });
+                cmd.entity(ship_id).despawn_recursive();
+            } else if **ship_health > 0 {
+                evw_effects_collisions.send(CollisionEffectEvent {
+                    avatar_a: Avatars::PlayerShip,
+                    transform_a: Some(*ship_transform),
+                    impact,
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+// How hard two bodies hit each other. Read after the step that resolved the hit, so the relative speed is
+// what they bounce apart with and the contact force is what the solver applied over that step
+fn impact(
+    ent_a: Entity,
+    ent_b: Entity,
+    q_bodies: &Query<(&Velocity, Option<&ReadMassProperties>)>,
+    rapier_context: &RapierContext,
+    dt: f32,
+) -> Impact {
+    let (Ok((velocity_a, mass_a)), Ok((velocity_b, mass_b))) =
+        (q_bodies.get(ent_a), q_bodies.get(ent_b))
+    else {
+        return Impact::default();
+    };
+    let mass = match (mass_a, mass_b) {
+        (Some(a), Some(b)) if a.get().mass + b.get().mass > 0. => {
+            Some(a.get().mass * b.get().mass / (a.get().mass + b.get().mass))
         }
+        _ => None,
+    };
+    let contact_force = rapier_context
+        .contact_pair(ent_a, ent_b)
+        .filter(|_| dt > 0.)
+        .map(|pair| {
+            let mut impulse = 0.;
+            for manifold in pair.manifolds() {
+                for point in manifold.points() {
+                    impulse += point.impulse();
+                }
+            }
+            impulse / dt
+        });
+    Impact {
+        relative_speed: Some((velocity_a.linvel - velocity_b.linvel).length()),
+        mass,
+        contact_force,
     }
 }
@@ -1,82 +1,136 @@
 use bevy::prelude::*;
-use bevy_particle_systems::{
-    ColorOverTime, Curve, CurvePoint, EmitterShape, JitteredValue, ParticleSystem,
-    ParticleSystemBundle, Playing,
-};
-use bevy_rapier2d::geometry::Collider;
+use bevy_particle_systems::Playing;
+use bevy_rapier2d::{geometry::Collider, plugin::PhysicsSet};
 use bevy_vector_shapes::{painter::ShapePainter, shapes::LinePainter};
 use noise::{NoiseFn, Perlin};
+use rand::Rng;
 
 use crate::{
+    animation::animation_plugin,
     archetypes::AsteroidSizes,
     avatars::{Asteroid, PlayerShip},
+    camera_shake::camera_shake_plugin,
+    combat_text::{combat_text_plugin, CombatTextEvent},
     components::{DespawnDelay, ProjectileTag, Score, ScoreboardUi},
-    controls::{ship_fire, ship_turn, thrust_ship},
+    controls::{
+        apply_flight_model, is_restart_requested, sample_keyboard_input, ship_fire, ship_turn,
+        thrust_ship, FlightModel, InputSet, KeyBindings, ShipInput,
+    },
+    effect_library::{effect_library_plugin, EffectParams, PersistentEffect},
     effects::{
-        handle_collision_effects, handle_destruction_effects, handle_thrust_effects,
-        CollisionEffectEvent, DestructionEffectEvent, ThrustEffectEvent,
+        count_live_particles, fade_debris, handle_collision_effects, handle_destruction_effects,
+        handle_thrust_effects, CollisionEffectEvent, DestructionEffectEvent, FireEffectEvent,
+        ParticleBudget, ParticleDensity, ParticleUsage, ThrustEffectEvent,
     },
     events::{CollisionAsteroidAsteroidEvent, CollisionProjectileEvent},
     game::{
         despawn_screen, AsteroidMaterialHandles, AsteroidMeshHandles, GameState, OnPlayScreen,
-        ParticlePixelTexture, PlayerShipTexture, StarComplexTexture, StarCoreTexture,
-        StarSimpleTexture, BOTTOM_WALL, LABEL_COLOR, LEFT_WALL, RIGHT_WALL, SCOREBOARD_FONT_SIZE,
-        SCOREBOARD_TEXT_PADDING, SCORE_COLOR, TOP_WALL,
+        PlayerShipTexture, StarComplexTexture, StarCoreTexture, StarSimpleTexture, BOTTOM_WALL,
+        LABEL_COLOR, LEFT_WALL, RIGHT_WALL, SCOREBOARD_FONT_SIZE, SCOREBOARD_TEXT_PADDING,
+        SCORE_COLOR, TOP_WALL, FIELD_ASTEROID_COUNT, FIELD_CLEAR_RADIUS,
+        FIELD_MAX_ASTEROID_SPEED, FIELD_MIN_ASTEROID_SPEED,
     },
+    hit_flash::hit_flash_plugin,
     physics::handle_collisions,
+    projectile_looks::projectile_looks_plugin,
+    rng::{GameRng, RngSeed},
+    ship_looks::ship_looks_plugin,
+    trails::trails_plugin,
     utils::Heading,
 };
 
 pub fn play_plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::Play), setup_play)
+    app.init_resource::<ShipInput>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<FlightModel>()
+        .init_resource::<PlayLayout>()
+        .add_systems(OnEnter(GameState::Play), setup_play)
         .add_systems(
             FixedUpdate,
             (
-                ship_turn,
-                thrust_ship,
-                wraparound,
-                ship_fire,
-                handle_collisions,
-                despawn_delay,
-            )
-                .chain()
-                .run_if(in_state(GameState::Play)),
-        )
-        .add_systems(
-            Update,
-            (
+                sample_keyboard_input.in_set(InputSet),
                 (
-                    draw_boundary,
-                    handle_collision_effects,
-                    handle_destruction_effects,
-                    handle_thrust_effects,
-                    update_scoreboard,
-                ),
-                // .run_if(in_state(GameState::Play)),
-                (despawn_screen::<OnPlayScreen>, setup_play)
+                    (despawn_screen::<OnPlayScreen>, setup_play)
+                        .chain()
+                        .run_if(is_restart_requested),
+                    apply_flight_model,
+                    ship_turn,
+                    thrust_ship,
+                    wraparound,
+                    ship_fire,
+                    handle_collisions,
+                    despawn_delay,
+                )
                     .chain()
-                    .run_if(press_r_restart_play),
-            ),
+                    .after(InputSet)
+                    .before(PhysicsSet::SyncBackend),
+            )
+                .run_if(in_state(GameState::Play)),
         )
         .add_systems(OnExit(GameState::Play), despawn_screen::<OnPlayScreen>)
         .add_event::<CollisionAsteroidAsteroidEvent>()
         .add_event::<CollisionProjectileEvent>()
         .add_event::<DestructionEffectEvent>()
         .add_event::<CollisionEffectEvent>()
-        .add_event::<ThrustEffectEvent>();
+        .add_event::<ThrustEffectEvent>()
+        .add_event::<FireEffectEvent>()
+        .add_event::<CombatTextEvent>();
+}
+
+// Perceivable side of play: drawing, sounds, particles and UI driven by the events play_plugin emits
+pub fn play_effects_plugin(app: &mut App) {
+    app.add_event::<DestructionEffectEvent>()
+        .add_event::<CollisionEffectEvent>()
+        .add_event::<ThrustEffectEvent>()
+        .init_resource::<ParticleDensity>()
+        .init_resource::<ParticleBudget>()
+        .init_resource::<ParticleUsage>()
+        .add_plugins((
+            animation_plugin,
+            effect_library_plugin,
+            camera_shake_plugin,
+            hit_flash_plugin,
+            projectile_looks_plugin,
+            ship_looks_plugin,
+            trails_plugin,
+            combat_text_plugin,
+        ))
+        .add_systems(PreUpdate, count_live_particles)
+        .add_systems(
+            Update,
+            (
+                draw_boundary,
+                handle_collision_effects,
+                handle_destruction_effects,
+                handle_thrust_effects,
+                fade_debris,
+                update_scoreboard,
+            ),
+            // .run_if(in_state(GameState::Play)),
+        );
+}
+
+// What setup_play spawns besides the ship
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayLayout {
+    // seeded random asteroid field
+    #[default]
+    Field,
+    // hand placed dev layout to exercise collisions and particles
+    Sandbox,
 }
 
 // mut meshes: ResMut<Assets<Mesh>>,
 // mut materials: ResMut<Assets<ColorMaterial>>,
 // playership_mesh_handle: Res<PlayerShipMeshHandle>,
 // playership_material_handle: Res<PlayerShipMaterialHandle>, // bg_music: Res<BackgroundMusic>,
+#[allow(clippy::too_many_arguments)]
 pub fn setup_play(
     mut cmd: Commands,
     asteroid_mesh_handles: Res<AsteroidMeshHandles>,
     asteroid_material_handles: Res<AsteroidMaterialHandles>,
     playership_texture: Res<PlayerShipTexture>,
     // white_material_handle: Res<WhiteMaterialHandle>,
-    particle_pixel_texture: Res<ParticlePixelTexture>,
     // powerup_core_texture: Res<PowerupCoreTexture>,
     // powerup_simple_texture: Res<PowerupSimpleTexture>,
     // powerup_complex_texture: Res<PowerupComplexTexture>,
@@ -86,58 +140,36 @@ pub fn setup_play(
     // green_planet_texture: Res<PlanetGreenTexture>,
     // grey_planet_texture: Res<PlanetGreyTexture>,
     // purple_planet_texture: Res<PlanetPurpleTexture>,
+    rng_seed: Res<RngSeed>,
+    layout: Res<PlayLayout>,
 ) {
+    // reseed on every (re)start so a given seed always produces the same layout
+    let mut rng = GameRng::from_seed(**rng_seed);
+
+    let ship_position = Vec2::new(0., -150.);
     PlayerShip::spawn(
-        0.,
-        -150.,
+        ship_position.x,
+        ship_position.y,
         None,
         &playership_texture,
-        &particle_pixel_texture,
         &mut cmd,
     );
 
-    // highly accessibly asteroid
-    Asteroid::spawn(
-        AsteroidSizes::Medium,
-        5,
-        0.,
-        100.,
-        None,
-        Some(0.),
-        asteroid_mesh_handles.0.clone(),
-        asteroid_material_handles.0.clone(),
-        &mut cmd,
-    );
-
-    dev_row_of_clashing_asteroids(
-        &mut cmd,
-        &asteroid_mesh_handles,
-        &asteroid_material_handles,
-    );
-
-    // Diagonal collision, see collision particles
-    Asteroid::spawn(
-        AsteroidSizes::Medium,
-        5,
-        LEFT_WALL + 50.,
-        BOTTOM_WALL + 300.,
-        None,
-        Some(0.),
-        asteroid_mesh_handles.0.clone(),
-        asteroid_material_handles.0.clone(),
-        &mut cmd,
-    );
-    Asteroid::spawn(
-        AsteroidSizes::Medium,
-        5,
-        LEFT_WALL + 130.,
-        BOTTOM_WALL + 230.,
-        None,
-        Some(0.),
-        asteroid_mesh_handles.0.clone(),
-        asteroid_material_handles.0.clone(),
-        &mut cmd,
-    );
+    match *layout {
+        PlayLayout::Field => spawn_asteroid_field(
+            &mut cmd,
+            ship_position,
+            &asteroid_mesh_handles,
+            &asteroid_material_handles,
+            &mut rng,
+        ),
+        PlayLayout::Sandbox => spawn_sandbox_asteroids(
+            &mut cmd,
+            &asteroid_mesh_handles,
+            &asteroid_material_handles,
+            &mut rng,
+        ),
+    }
 
     cmd
         .spawn((
@@ -171,8 +203,14 @@ pub fn setup_play(
         &star_core_texture,
         &star_simple_texture,
         &star_complex_texture,
+        **rng_seed,
+        &mut rng,
     );
-    spawn_cosmic_wind(300., -400., None, &mut cmd, &particle_pixel_texture);
+    spawn_cosmic_wind(300., -400., None, &mut cmd);
+
+    cmd.insert_resource(rng);
+    // every (re)start, including coming back from the main menu, is a new game
+    cmd.insert_resource(Score(0));
 
     // Simple powerup, large and easy to get
     // spawn_core_powerup(-200., 0., &mut commands, &powerup_core_texture);
@@ -232,19 +270,16 @@ pub fn update_scoreboard(scoreboard: Res<Score>, mut query: Query<&mut Text, Wit
 pub fn despawn_delay(
     mut cmd: Commands,
     mut query: Query<(Entity, &mut DespawnDelay), With<ProjectileTag>>,
-    time: Res<Time>,
+    time: Res<Time<Fixed>>,
 ) {
     for (entity, mut despawn_delay) in &mut query {
         if despawn_delay.tick(time.delta()).just_finished() {
-            cmd.entity(entity).despawn();
+            // with the projectile's visuals
+            cmd.entity(entity).despawn_recursive();
         }
     }
 }
 
-pub fn press_r_restart_play(keyboard_input: Res<ButtonInput<KeyCode>>) -> bool {
-    keyboard_input.just_pressed(KeyCode::KeyR)
-}
-
 fn spawn_core_star(
     x: f32,
     y: f32,
@@ -377,53 +412,105 @@ fn spawn_complex_star(
 //     ));
 // }
 
-fn spawn_cosmic_wind(
-    x: f32,
-    y: f32,
-    heading: Option<Heading>,
-    commands: &mut Commands,
-    particle_pixel_texture: &ParticlePixelTexture,
+fn spawn_cosmic_wind(x: f32, y: f32, heading: Option<Heading>, commands: &mut Commands) {
+    commands.spawn((
+        PersistentEffect::new("cosmic_wind").with_params(
+            EffectParams::default().with_direction(heading.unwrap_or_default().to_radians()),
+        ),
+        TransformBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
+        Playing,
+        OnPlayScreen,
+    ));
+}
+
+fn spawn_sandbox_asteroids(
+    cmd: &mut Commands,
+    asteroid_mesh_handles: &AsteroidMeshHandles,
+    asteroid_material_handles: &AsteroidMaterialHandles,
+    rng: &mut GameRng,
 ) {
-    commands
-        .spawn(ParticleSystemBundle {
-            particle_system: ParticleSystem {
-                max_particles: 1000,
-                texture: particle_pixel_texture.0.clone().into(),
-                spawn_rate_per_second: 100.0.into(),
-                initial_speed: JitteredValue::jittered(200.0, -50.0..0.0),
-                lifetime: JitteredValue::jittered(4.0, -3.0..0.0),
-                // color: ColorOverTime::Gradient(Curve::new(vec![
-                //     CurvePoint::new(Color::PURPLE, 0.0),
-                //     CurvePoint::new(Color::RED, 0.5),
-                //     CurvePoint::new(Color::rgba(0.0, 0.0, 1.0, 0.0), 1.0),
-                // ])),
-                color: ColorOverTime::Gradient(Curve::new(vec![
-                    CurvePoint::new(Color::rgba(1., 1., 1., 0.0), 0.0),
-                    CurvePoint::new(Color::rgba(1., 1., 1., 1.), 0.5),
-                    CurvePoint::new(Color::rgba(1., 1., 1., 0.0), 1.0),
-                ])),
-                // color: ColorOverTime::Constant(Color::WHITE),
-                emitter_shape: EmitterShape::line(300.0, heading.unwrap_or_default().to_radians()),
-                looping: true,
-                despawn_on_finish: true, // true for one-shot, false for reuse like weapons fire...?
-                // rotate_to_movement_direction: true,
-                // initial_rotation: (0.0_f32).to_radians().into(),
-                system_duration_seconds: 5.0,
-                max_distance: Some(1000.0),
-                scale: 2.0.into(),
-                ..ParticleSystem::default()
-            },
-            transform: Transform::from_xyz(x, y, 0.0),
-            ..ParticleSystemBundle::default()
-        })
-        .insert(Playing)
+    // highly accessibly asteroid
+    cmd
+        .spawn(Asteroid::bundle(
+            AsteroidSizes::Medium,
+            5,
+            Vec2::new(0., 100.),
+            Vec2::ZERO,
+            &asteroid_mesh_handles.0,
+            &asteroid_material_handles.0,
+            rng,
+        ))
+        .insert(OnPlayScreen);
+
+    dev_row_of_clashing_asteroids(cmd, asteroid_mesh_handles, asteroid_material_handles, rng);
+
+    // Diagonal collision, see collision particles
+    cmd
+        .spawn(Asteroid::bundle(
+            AsteroidSizes::Medium,
+            5,
+            Vec2::new(LEFT_WALL + 50., BOTTOM_WALL + 300.),
+            Vec2::ZERO,
+            &asteroid_mesh_handles.0,
+            &asteroid_material_handles.0,
+            rng,
+        ))
+        .insert(OnPlayScreen);
+    cmd
+        .spawn(Asteroid::bundle(
+            AsteroidSizes::Medium,
+            5,
+            Vec2::new(LEFT_WALL + 130., BOTTOM_WALL + 230.),
+            Vec2::ZERO,
+            &asteroid_mesh_handles.0,
+            &asteroid_material_handles.0,
+            rng,
+        ))
         .insert(OnPlayScreen);
 }
 
+fn spawn_asteroid_field(
+    cmd: &mut Commands,
+    ship_position: Vec2,
+    asteroid_mesh_handles: &AsteroidMeshHandles,
+    asteroid_material_handles: &AsteroidMaterialHandles,
+    rng: &mut GameRng,
+) {
+    let sizes = [
+        AsteroidSizes::Small,
+        AsteroidSizes::Medium,
+        AsteroidSizes::Large,
+    ];
+    let n_sides = [5, 6, 8];
+    let mut n_spawned = 0;
+    while n_spawned < FIELD_ASTEROID_COUNT {
+        let position = Vec2::new(
+            rng.gen_range(LEFT_WALL..RIGHT_WALL),
+            rng.gen_range(BOTTOM_WALL..TOP_WALL),
+        );
+        if position.distance(ship_position) < FIELD_CLEAR_RADIUS {
+            continue;
+        }
+        cmd
+            .spawn(Asteroid::bundle(
+                sizes[rng.gen_range(0..sizes.len())],
+                n_sides[rng.gen_range(0..n_sides.len())],
+                position,
+                Heading(rng.gen_range(0.0..360.0)).linvel(rng.gen_range(FIELD_MIN_ASTEROID_SPEED..FIELD_MAX_ASTEROID_SPEED)),
+                &asteroid_mesh_handles.0,
+                &asteroid_material_handles.0,
+                rng,
+            ))
+            .insert(OnPlayScreen);
+        n_spawned += 1;
+    }
+}
+
 fn dev_row_of_clashing_asteroids(
     cmd: &mut Commands,
     asteroid_mesh_handles: &AsteroidMeshHandles,
     asteroid_material_handles: &AsteroidMaterialHandles,
+    rng: &mut GameRng,
 ) {
     let start_x = LEFT_WALL + 50.;
     let dx = 250.;
@@ -438,38 +525,40 @@ fn dev_row_of_clashing_asteroids(
         (AsteroidSizes::Large, AsteroidSizes::Large),
     ];
     for (i, (size_a, size_b)) in pairs.iter().enumerate() {
-        Asteroid::spawn(
-            *size_a,
-            5,
-            start_x + (dx * i as f32),
-            y,
-            Some(Heading(-90.)),
-            Some(20.),
-            asteroid_mesh_handles.0.clone(),
-            asteroid_material_handles.0.clone(),
-            cmd,
-        );
-        Asteroid::spawn(
-            *size_b,
-            5,
-            start_x + (dx * i as f32),
-            y - separation_y,
-            Some(Heading(90.)),
-            Some(20.),
-            asteroid_mesh_handles.0.clone(),
-            asteroid_material_handles.0.clone(),
-            cmd,
-        );
+        cmd
+            .spawn(Asteroid::bundle(
+                *size_a,
+                5,
+                Vec2::new(start_x + (dx * i as f32), y),
+                Heading(-90.).linvel(20.),
+                &asteroid_mesh_handles.0,
+                &asteroid_material_handles.0,
+                rng,
+            ))
+            .insert(OnPlayScreen);
+        cmd
+            .spawn(Asteroid::bundle(
+                *size_b,
+                5,
+                Vec2::new(start_x + (dx * i as f32), y - separation_y),
+                Heading(90.).linvel(20.),
+                &asteroid_mesh_handles.0,
+                &asteroid_material_handles.0,
+                rng,
+            ))
+            .insert(OnPlayScreen);
     }
 }
 
 fn spawn_cosmic_background(
-    mut cmd: &mut Commands,
+    cmd: &mut Commands,
     star_core_texture: &StarCoreTexture,
     star_simple_texture: &StarSimpleTexture,
     star_complex_texture: &StarComplexTexture,
+    seed: u64,
+    rng: &mut GameRng,
 ) {
-    let noise = Perlin::new(0);
+    let noise = Perlin::new(seed as u32);
     let width = RIGHT_WALL - LEFT_WALL;
     let height = TOP_WALL - BOTTOM_WALL;
     let step = 50; // simple density
@@ -502,20 +591,20 @@ fn spawn_cosmic_background(
             //     + 1.0 / 2.0);
 
             // stars only show when a noised value is above an arbitrary threshold
-            if rand::random::<f32>() > (1. - density_core) {
+            if rng.gen::<f32>() > (1. - density_core) {
                 spawn_core_star(
                     x as f32 - (width / 2.) + dx,
                     y as f32 - (height / 2.) + dy,
                     Some(energy),
                     None,
-                    &mut cmd,
-                    &star_core_texture,
+                    cmd,
+                    star_core_texture,
                 );
-            } else if rand::random::<f32>() > (1. - density_simple) {
-                let rng = rand::random::<f32>();
-                let color = if rng < 0.4 {
+            } else if rng.gen::<f32>() > (1. - density_simple) {
+                let roll = rng.gen::<f32>();
+                let color = if roll < 0.4 {
                     Some(Color::hsl(250., 0.0, 0.0))
-                } else if rng < 0.8 {
+                } else if roll < 0.8 {
                     Some(Color::hsl(280., 0.0, 0.0))
                 } else {
                     None
@@ -525,18 +614,18 @@ fn spawn_cosmic_background(
                     y as f32 - (height / 2.) + dy,
                     Some(energy),
                     color,
-                    &mut cmd,
-                    &star_simple_texture,
+                    cmd,
+                    star_simple_texture,
                 );
-            } else if rand::random::<f32>() > (1. - density_complex) {
-                let rng = rand::random::<f32>();
-                let color = if rng < 0.2 {
+            } else if rng.gen::<f32>() > (1. - density_complex) {
+                let roll = rng.gen::<f32>();
+                let color = if roll < 0.2 {
                     Color::hsl(55., 0., 0.)
-                } else if rng < 0.4 {
+                } else if roll < 0.4 {
                     Color::hsl(220., 0., 0.)
-                } else if rng < 0.6 {
+                } else if roll < 0.6 {
                     Color::hsl(320., 0., 0.3)
-                } else if rng < 0.8 {
+                } else if roll < 0.8 {
                     Color::hsl(0., 0., 0.)
                 } else {
                     Color::WHITE
@@ -546,8 +635,8 @@ fn spawn_cosmic_background(
                     y as f32 - (height / 2.) + dy,
                     Some(energy),
                     Some(color),
-                    &mut cmd,
-                    &star_complex_texture,
+                    cmd,
+                    star_complex_texture,
                 );
             }
         }
@@ -0,0 +1,125 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    animation::{attach_sprite_animations, SpriteAnimation},
+    components::{FireType, ProjectileTag},
+    trails::Trail,
+};
+
+// How projectiles look, per weapon: a sprite animation, see assets/animations, and an optional trail
+// drawn behind them. Gameplay only spawns the bare projectile with its FireType, the look is put on a
+// child entity here so it can be turned to match the sheet without touching the projectile's own
+// transform. The bare sprite stays until the animation is showing, and for good if it never does,
+// e.g. when the animation library fails to load.
+
+// the sheet's projectiles fly to the right, projectiles fly along their local y
+const SHEET_ROTATION: f32 = FRAC_PI_2;
+// positions a trail remembers, one per frame
+const MAX_TRAIL_POINTS: usize = 24;
+
+pub fn projectile_looks_plugin(app: &mut App) {
+    app.init_resource::<ProjectileLooks>().add_systems(
+        Update,
+        (
+            dress_projectiles.before(attach_sprite_animations),
+            hide_bare_projectiles.after(attach_sprite_animations),
+        ),
+    );
+}
+
+#[derive(Clone, Debug)]
+pub struct ProjectileLook {
+    pub animation: &'static str,
+    // side of the drawn frame in the projectile's own units, before its transform's scale
+    pub size: f32,
+    pub trail: Option<Trail>,
+}
+
+impl ProjectileLook {
+    pub fn primary() -> Self {
+        Self {
+            animation: "bolt_green",
+            size: 10.,
+            trail: Some(Trail::new(
+                Color::rgba(0.6, 1., 0.5, 0.6),
+                2.,
+                0.12,
+                MAX_TRAIL_POINTS,
+            )),
+        }
+    }
+
+    pub fn secondary() -> Self {
+        Self {
+            animation: "orb_cyan",
+            size: 12.,
+            trail: None,
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct ProjectileLooks(pub HashMap<FireType, ProjectileLook>);
+
+impl Default for ProjectileLooks {
+    fn default() -> Self {
+        Self(HashMap::from_iter([
+            (FireType::Primary, ProjectileLook::primary()),
+            (FireType::Secondary, ProjectileLook::secondary()),
+        ]))
+    }
+}
+
+// On the projectile, the child showing it
+#[derive(Component)]
+pub struct ProjectileVisual(pub Entity);
+
+// Puts the weapon's look on projectiles, hidden until its animation has a sheet
+#[allow(clippy::type_complexity)]
+pub fn dress_projectiles(
+    mut cmd: Commands,
+    looks: Res<ProjectileLooks>,
+    q_projectiles: Query<(Entity, &FireType), (With<ProjectileTag>, Without<ProjectileVisual>)>,
+) {
+    for (entity, fire_type) in &q_projectiles {
+        let Some(look) = looks.get(fire_type) else {
+            continue;
+        };
+        let visual = cmd
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(look.size)),
+                        ..default()
+                    },
+                    transform: Transform::from_rotation(Quat::from_rotation_z(SHEET_ROTATION)),
+                    // until the animation has its sheet
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                SpriteAnimation::new(look.animation),
+            ))
+            .id();
+        cmd.entity(entity)
+            .add_child(visual)
+            .insert(ProjectileVisual(visual));
+        if let Some(trail) = &look.trail {
+            cmd.entity(entity).insert(trail.clone());
+        }
+    }
+}
+
+// Swaps the bare sprite gameplay spawns projectiles with for their look once it's showing. The collider
+// still goes by the projectile's transform, only its sprite is hidden
+pub fn hide_bare_projectiles(
+    mut q_projectiles: Query<(&ProjectileVisual, &mut Sprite), With<ProjectileTag>>,
+    q_showing: Query<(), With<TextureAtlas>>,
+) {
+    for (visual, mut sprite) in &mut q_projectiles {
+        if sprite.color != Color::NONE && q_showing.contains(visual.0) {
+            sprite.color = Color::NONE;
+        }
+    }
+}
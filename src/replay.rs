@@ -0,0 +1,597 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_rapier2d::{
+    dynamics::{RigidBody, Velocity},
+    plugin::PhysicsSet,
+};
+
+use crate::{
+    components::Score,
+    controls::{sample_keyboard_input, ActionBits, FlightModel, InputSet, ShipInput},
+    effects::Debris,
+    game::GameState,
+    play::PlayLayout,
+    rng::RngSeed,
+};
+
+// Replays rely on the simulation being deterministic: given the same seed, layout, timestep and per-tick inputs,
+// every run produces the same world. A replay file therefore only stores those, plus periodic world
+// checksums so playback can detect when it has drifted (desynced) from the recorded run.
+
+const REPLAY_MAGIC: &[u8; 4] = b"AION";
+const REPLAY_VERSION: u16 = 1;
+pub const DEFAULT_CHECKSUM_INTERVAL: u32 = 64;
+// a day of play at the default 64Hz, longer replays are taken to be corrupt rather than expanded in memory
+pub const MAX_REPLAY_TICKS: u64 = 64 * 60 * 60 * 24;
+const DATA_DIR: &str = "aion";
+const REPLAYS_DIR: &str = "replays";
+const LAST_REPLAY_FILE: &str = "last.aionreplay";
+pub const MIN_PLAYBACK_SPEED: f32 = 0.25;
+pub const MAX_PLAYBACK_SPEED: f32 = 8.0;
+
+pub fn replay_plugin(app: &mut App) {
+    app.init_resource::<ReplayMode>()
+        .init_resource::<ReplayTick>()
+        .add_systems(PreStartup, setup_replay)
+        .add_systems(OnEnter(GameState::Play), restart_replay)
+        .add_systems(
+            FixedUpdate,
+            (
+                feed_replay_input
+                    .in_set(InputSet)
+                    .after(sample_keyboard_input)
+                    .run_if(resource_exists::<ReplayPlayback>),
+                record_replay_input
+                    .after(InputSet)
+                    .run_if(resource_exists::<ReplayRecorder>),
+                checksum_replay_tick.after(PhysicsSet::Writeback),
+            )
+                .run_if(in_state(GameState::Play)),
+        )
+        .add_systems(
+            Update,
+            playback_speed_controls.run_if(resource_exists::<ReplayPlayback>),
+        )
+        .add_systems(
+            Last,
+            save_replay_on_exit.run_if(resource_exists::<ReplayRecorder>),
+        );
+}
+
+// Where the record_replays setting records to, each run over the last one
+pub fn default_replay_path() -> PathBuf {
+    match dirs::data_dir() {
+        Some(dir) => dir.join(DATA_DIR).join(REPLAYS_DIR).join(LAST_REPLAY_FILE),
+        None => PathBuf::from(LAST_REPLAY_FILE),
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+
+// Number of fixed ticks simulated in play since startup
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ReplayTick(pub u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub layout: PlayLayout,
+    pub timestep: Duration,
+    pub checksum_interval: u32,
+    pub inputs: Vec<ActionBits>,
+    // (tick, model) for every change of the flight model, the first one at tick 0
+    pub flight_models: Vec<(u64, FlightModel)>,
+    // checksums[i] is taken after tick (i + 1) * checksum_interval
+    pub checksums: Vec<u64>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, layout: PlayLayout, timestep: Duration) -> Self {
+        Self {
+            seed,
+            layout,
+            timestep,
+            checksum_interval: DEFAULT_CHECKSUM_INTERVAL,
+            inputs: vec![],
+            flight_models: vec![],
+            checksums: vec![],
+        }
+    }
+
+    pub fn flight_model_at(&self, tick: u64) -> FlightModel {
+        self.flight_models
+            .iter()
+            .rev()
+            .find(|(change_tick, _)| *change_tick <= tick)
+            .map(|(_, flight_model)| *flight_model)
+            .unwrap_or_default()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(REPLAY_MAGIC)?;
+        w.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        w.write_all(&(self.timestep.as_micros() as u64).to_le_bytes())?;
+        w.write_all(&self.checksum_interval.to_le_bytes())?;
+        w.write_all(&[layout_to_byte(self.layout)])?;
+
+        // held keys rarely change between ticks, so inputs are stored as (run length, bits) pairs
+        let runs = encode_runs(&self.inputs);
+        w.write_all(&(runs.len() as u32).to_le_bytes())?;
+        for (len, bits) in runs {
+            w.write_all(&len.to_le_bytes())?;
+            w.write_all(&[bits.0])?;
+        }
+
+        w.write_all(&(self.flight_models.len() as u32).to_le_bytes())?;
+        for (tick, flight_model) in &self.flight_models {
+            w.write_all(&tick.to_le_bytes())?;
+            w.write_all(&[flight_model_to_byte(*flight_model)])?;
+        }
+
+        w.write_all(&(self.checksums.len() as u32).to_le_bytes())?;
+        for checksum in &self.checksums {
+            w.write_all(&checksum.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        let version = u16::from_le_bytes(read_bytes(r)?);
+        if version != REPLAY_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported replay version {version}, expected {REPLAY_VERSION}"
+            )));
+        }
+        let seed = u64::from_le_bytes(read_bytes(r)?);
+        let timestep = Duration::from_micros(u64::from_le_bytes(read_bytes(r)?));
+        let checksum_interval = u32::from_le_bytes(read_bytes(r)?);
+        let [byte] = read_bytes::<1>(r)?;
+        let layout = layout_from_byte(byte).ok_or_else(|| invalid_data("unknown play layout"))?;
+        if timestep.is_zero() || checksum_interval == 0 {
+            return Err(invalid_data("corrupt replay header"));
+        }
+
+        // counts come from the file, nothing is reserved up front so a truncated file fails on reading instead
+        let n_runs = u32::from_le_bytes(read_bytes(r)?);
+        let mut inputs = vec![];
+        for _ in 0..n_runs {
+            let len = u32::from_le_bytes(read_bytes(r)?);
+            let [bits] = read_bytes::<1>(r)?;
+            if inputs.len() as u64 + len as u64 > MAX_REPLAY_TICKS {
+                return Err(invalid_data(&format!(
+                    "replay longer than {MAX_REPLAY_TICKS} ticks"
+                )));
+            }
+            inputs.extend(std::iter::repeat_n(ActionBits(bits), len as usize));
+        }
+
+        let n_changes = u32::from_le_bytes(read_bytes(r)?);
+        let mut flight_models = vec![];
+        for _ in 0..n_changes {
+            let tick = u64::from_le_bytes(read_bytes(r)?);
+            let [byte] = read_bytes::<1>(r)?;
+            let flight_model =
+                flight_model_from_byte(byte).ok_or_else(|| invalid_data("unknown flight model"))?;
+            flight_models.push((tick, flight_model));
+        }
+
+        let n_checksums = u32::from_le_bytes(read_bytes(r)?);
+        let mut checksums = vec![];
+        for _ in 0..n_checksums {
+            checksums.push(u64::from_le_bytes(read_bytes(r)?));
+        }
+
+        Ok(Self {
+            seed,
+            layout,
+            timestep,
+            checksum_interval,
+            inputs,
+            flight_models,
+            checksums,
+        })
+    }
+}
+
+fn encode_runs(inputs: &[ActionBits]) -> Vec<(u32, ActionBits)> {
+    let mut runs: Vec<(u32, ActionBits)> = vec![];
+    for bits in inputs {
+        if let Some((len, last)) = runs.last_mut() {
+            if last == bits && *len < u32::MAX {
+                *len += 1;
+                continue;
+            }
+        }
+        runs.push((1, *bits));
+    }
+    runs
+}
+
+fn layout_to_byte(layout: PlayLayout) -> u8 {
+    match layout {
+        PlayLayout::Field => 0,
+        PlayLayout::Sandbox => 1,
+    }
+}
+
+fn layout_from_byte(byte: u8) -> Option<PlayLayout> {
+    match byte {
+        0 => Some(PlayLayout::Field),
+        1 => Some(PlayLayout::Sandbox),
+        _ => None,
+    }
+}
+
+fn flight_model_to_byte(flight_model: FlightModel) -> u8 {
+    match flight_model {
+        FlightModel::Assisted => 0,
+        FlightModel::Newtonian => 1,
+    }
+}
+
+fn flight_model_from_byte(byte: u8) -> Option<FlightModel> {
+    match byte {
+        0 => Some(FlightModel::Assisted),
+        1 => Some(FlightModel::Newtonian),
+        _ => None,
+    }
+}
+
+fn read_bytes<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    pub path: PathBuf,
+    pub replay: Replay,
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub speed: f32,
+    pub is_desynced: bool,
+    pub is_finished: bool,
+}
+
+// Runs before Startup so the seed, layout and timestep are in place before physics is configured and play is set up
+pub fn setup_replay(
+    mut cmd: Commands,
+    mut mode: ResMut<ReplayMode>,
+    mut rng_seed: ResMut<RngSeed>,
+    mut layout: ResMut<PlayLayout>,
+    mut time: ResMut<Time<Fixed>>,
+) {
+    match mode.clone() {
+        ReplayMode::Off => {}
+        ReplayMode::Record(path) => {
+            cmd.insert_resource(ReplayRecorder {
+                path,
+                replay: Replay::new(**rng_seed, *layout, time.timestep()),
+            });
+        }
+        ReplayMode::Playback(path) => match Replay::load(&path) {
+            Ok(replay) => {
+                info!(
+                    "playing back replay {:?}: {} ticks, seed {}, {:?} layout",
+                    path,
+                    replay.inputs.len(),
+                    replay.seed,
+                    replay.layout
+                );
+                **rng_seed = replay.seed;
+                *layout = replay.layout;
+                time.set_timestep(replay.timestep);
+                cmd.insert_resource(ReplayPlayback {
+                    replay,
+                    speed: 1.0,
+                    is_desynced: false,
+                    is_finished: false,
+                });
+            }
+            Err(e) => {
+                error!("failed to load replay {:?}: {}", path, e);
+                *mode = ReplayMode::Off;
+            }
+        },
+    }
+}
+
+// Entering play again (e.g. from the main menu) starts a new game, and with it a new recording or playback
+pub fn restart_replay(
+    mut tick: ResMut<ReplayTick>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    flight_model: Res<FlightModel>,
+) {
+    if **tick == 0 {
+        return;
+    }
+    **tick = 0;
+    if let Some(mut recorder) = recorder {
+        let replay = &mut recorder.replay;
+        replay.inputs.clear();
+        replay.flight_models = vec![(0, *flight_model)];
+        replay.checksums.clear();
+    }
+    if let Some(mut playback) = playback {
+        playback.is_desynced = false;
+        playback.is_finished = false;
+    }
+}
+
+pub fn feed_replay_input(
+    mut ship_input: ResMut<ShipInput>,
+    mut flight_model: ResMut<FlightModel>,
+    mut playback: ResMut<ReplayPlayback>,
+    tick: Res<ReplayTick>,
+) {
+    flight_model.set_if_neq(playback.replay.flight_model_at(**tick));
+    match playback.replay.inputs.get(**tick as usize) {
+        Some(bits) => ship_input.current = *bits,
+        None => {
+            ship_input.current = ActionBits::default();
+            if !playback.is_finished {
+                playback.is_finished = true;
+                info!("replay finished at tick {}", **tick);
+            }
+        }
+    }
+}
+
+pub fn record_replay_input(
+    ship_input: Res<ShipInput>,
+    flight_model: Res<FlightModel>,
+    tick: Res<ReplayTick>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    recorder.replay.inputs.push(ship_input.current);
+    if flight_model.is_changed() {
+        recorder.replay.flight_models.push((**tick, *flight_model));
+    }
+}
+
+pub fn checksum_replay_tick(
+    mut tick: ResMut<ReplayTick>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    q_bodies: GameplayBodies,
+    score: Res<Score>,
+) {
+    **tick += 1;
+
+    if let Some(mut recorder) = recorder {
+        if (**tick).is_multiple_of(recorder.replay.checksum_interval as u64) {
+            let checksum = world_checksum(&q_bodies, &score);
+            recorder.replay.checksums.push(checksum);
+        }
+    }
+
+    if let Some(mut playback) = playback {
+        let interval = playback.replay.checksum_interval as u64;
+        if playback.is_desynced || !(**tick).is_multiple_of(interval) {
+            return;
+        }
+        let index = (**tick / interval - 1) as usize;
+        if let Some(expected) = playback.replay.checksums.get(index).copied() {
+            if world_checksum(&q_bodies, &score) != expected {
+                playback.is_desynced = true;
+                warn!("replay desynced at tick {}", **tick);
+            }
+        }
+    }
+}
+
+// Physics bodies that are part of gameplay. Debris is left out: effects spawn it in Update, so how much
+// there is depends on the frame rate and chance, and there's none headless or with reduced effects. It
+// only collides with other debris, so gameplay bodies move the same with or without it.
+pub type GameplayBodies<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static Velocity), (With<RigidBody>, Without<Debris>)>;
+
+// Order independent hash of all gameplay bodies and the score. Entity ids are not hashed since effects
+// spawned in Update (frame rate dependent) shift entity allocation between otherwise identical runs.
+pub fn world_checksum(q_bodies: &GameplayBodies, score: &Score) -> u64 {
+    let mut checksum = fnv1a(&(**score as u64).to_le_bytes());
+    for (transform, velocity) in q_bodies.iter() {
+        let values = [
+            transform.translation.x,
+            transform.translation.y,
+            transform.rotation.z,
+            transform.rotation.w,
+            velocity.linvel.x,
+            velocity.linvel.y,
+            velocity.angvel,
+        ];
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_bits().to_le_bytes())
+            .collect();
+        checksum = checksum.wrapping_add(fnv1a(&bytes));
+    }
+    checksum
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// [ / ] halve / double playback speed, P pauses
+pub fn playback_speed_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        playback.speed = (playback.speed / 2.).max(MIN_PLAYBACK_SPEED);
+        time.set_relative_speed(playback.speed);
+        info!("replay speed {}x", playback.speed);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        playback.speed = (playback.speed * 2.).min(MAX_PLAYBACK_SPEED);
+        time.set_relative_speed(playback.speed);
+        info!("replay speed {}x", playback.speed);
+    }
+    if keys.just_pressed(KeyCode::KeyP) {
+        if time.is_paused() {
+            time.unpause();
+        } else {
+            time.pause();
+        }
+    }
+}
+
+pub fn save_replay_on_exit(mut evr_exit: EventReader<AppExit>, recorder: Res<ReplayRecorder>) {
+    if evr_exit.read().next().is_some() {
+        match recorder.replay.save(&recorder.path) {
+            Ok(()) => info!(
+                "saved replay {:?} ({} ticks)",
+                recorder.path,
+                recorder.replay.inputs.len()
+            ),
+            Err(e) => error!("failed to save replay {:?}: {}", recorder.path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: ActionBits = ActionBits(0);
+    const THRUST: ActionBits = ActionBits(1);
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(7, PlayLayout::Field, Duration::from_micros(15625));
+        replay.inputs = vec![IDLE, IDLE, IDLE, THRUST, THRUST, IDLE];
+        replay.flight_models = vec![(0, FlightModel::Assisted), (4, FlightModel::Newtonian)];
+        replay.checksums = vec![0xdead_beef, 42];
+        replay
+    }
+
+    fn write(replay: &Replay) -> Vec<u8> {
+        let mut bytes = vec![];
+        replay.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn header(version: u16, layout: u8) -> Vec<u8> {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(7u64.to_le_bytes());
+        bytes.extend(15625u64.to_le_bytes());
+        bytes.extend(DEFAULT_CHECKSUM_INTERVAL.to_le_bytes());
+        bytes.push(layout);
+        bytes
+    }
+
+    fn runs(bytes: &mut Vec<u8>, runs: &[(u32, u8)]) {
+        bytes.extend((runs.len() as u32).to_le_bytes());
+        for (len, bits) in runs {
+            bytes.extend(len.to_le_bytes());
+            bytes.push(*bits);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = replay();
+        let read = Replay::read_from(&mut write(&replay).as_slice()).unwrap();
+        assert_eq!(read, replay);
+    }
+
+    #[test]
+    fn merges_held_inputs_into_runs() {
+        let replay = replay();
+        assert_eq!(
+            encode_runs(&replay.inputs),
+            vec![(3, IDLE), (2, THRUST), (1, IDLE)]
+        );
+        // header, 3 runs of 5 bytes, 2 flight model changes of 9 bytes and 2 checksums, each with its count
+        let n_bytes = header(REPLAY_VERSION, 0).len() + 4 + 3 * 5 + 4 + 2 * 9 + 4 + 2 * 8;
+        assert_eq!(write(&replay).len(), n_bytes);
+    }
+
+    #[test]
+    fn rejects_corrupt_header() {
+        let mut bytes = write(&replay());
+        bytes[0] = b'X';
+        assert!(Replay::read_from(&mut bytes.as_slice()).is_err());
+
+        let bytes = header(REPLAY_VERSION + 1, 0);
+        assert!(Replay::read_from(&mut bytes.as_slice()).is_err());
+
+        let bytes = header(REPLAY_VERSION, 9);
+        assert!(Replay::read_from(&mut bytes.as_slice()).is_err());
+
+        let mut bytes = header(REPLAY_VERSION, 0);
+        bytes[14..22].copy_from_slice(&0u64.to_le_bytes());
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_oversized_counts() {
+        let mut bytes = header(REPLAY_VERSION, 0);
+        runs(&mut bytes, &[(u32::MAX, 1)]);
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // runs that are each fine but add up to too many ticks
+        let mut bytes = header(REPLAY_VERSION, 0);
+        let half = (MAX_REPLAY_TICKS / 2 + 1) as u32;
+        runs(&mut bytes, &[(half, 0), (half, 1)]);
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // counts past the end of a truncated file
+        let mut bytes = header(REPLAY_VERSION, 0);
+        runs(&mut bytes, &[]);
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(u32::MAX.to_le_bytes());
+        let error = Replay::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::game::DEFAULT_RNG_SEED;
+
+// All gameplay randomness (asteroid spin, star placement, spawns) is drawn from GameRng so that
+// identical seeds and inputs produce identical runs. Never use rand::random or thread_rng in gameplay code.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub ChaCha8Rng);
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl FromWorld for GameRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource::<RngSeed>().copied().unwrap_or_default();
+        Self::from_seed(*seed)
+    }
+}
+
+#[derive(Resource, Copy, Clone, Debug, Deref, DerefMut)]
+pub struct RngSeed(pub u64);
+
+impl Default for RngSeed {
+    fn default() -> Self {
+        Self(DEFAULT_RNG_SEED)
+    }
+}
@@ -0,0 +1,80 @@
+use std::{error::Error, fmt, io, marker::PhantomData};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+// Game data written by hand as RON files under assets/: the sound bank, effect presets and sprite
+// animations. The asset server watches the files (bevy's file_watcher feature) and reloads one whenever
+// it changes, so sounds, effects and animations can be tuned while the game runs. Systems that keep
+// something built from the data pick the change up from AssetEvent::Modified.
+
+// Asset read from a RON file as its Def, then turned into the asset by from_def
+pub trait RonAsset: Asset + Sized {
+    // the file as written, with asset paths where the asset holds handles
+    type Def: DeserializeOwned;
+
+    // e.g. "effects.ron", picks the loader by the file name
+    const EXTENSIONS: &'static [&'static str];
+
+    // loads what the file refers to, and checks what serde can't
+    fn from_def(def: Self::Def, load_context: &mut LoadContext) -> Result<Self, RonAssetError>;
+}
+
+pub struct RonAssetLoader<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for RonAssetLoader<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[derive(Debug)]
+pub enum RonAssetError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    // parsed, but doesn't make sense, e.g. an animation on a sheet that isn't there
+    Invalid(String),
+}
+
+impl fmt::Display for RonAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonAssetError::Io(e) => write!(f, "failed to read: {}", e),
+            RonAssetError::Ron(e) => write!(f, "invalid RON: {}", e),
+            RonAssetError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for RonAssetError {}
+
+impl<T: RonAsset> AssetLoader for RonAssetLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = RonAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<T, RonAssetError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(RonAssetError::Io)?;
+            let def: T::Def = ron::de::from_bytes(&bytes).map_err(RonAssetError::Ron)?;
+            T::from_def(def, load_context)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
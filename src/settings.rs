@@ -0,0 +1,395 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::AudioMixer,
+    camera_shake::CameraShake,
+    controls::{FlightModel, KeyBindings},
+    debug::ShowDebugDisplay,
+    display::WindowGeometry,
+    effects::{ParticleBudget, ParticleDensity, DEFAULT_PARTICLE_BUDGET},
+    game::{LOGICAL_HEIGHT, LOGICAL_WIDTH},
+};
+
+// User preferences, stored as RON in the platform config directory (or the --config path).
+// Loaded while the app is built, so everything from the window to load_assets already sees them,
+// and written back once the Settings resource has stopped changing for SETTINGS_SAVE_DELAY, or on exit.
+//
+// Every section is #[serde(default)]: fields missing from an older file keep their defaults, unknown
+// fields are ignored. Changes that can't be expressed that way bump SETTINGS_VERSION and go in migrate.
+
+pub const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_DIR: &str = "aion";
+const SETTINGS_FILE: &str = "settings.ron";
+// dragging the window or a volume slider changes settings every frame, they're saved once it stops
+pub const SETTINGS_SAVE_DELAY: f32 = 0.5;
+
+pub fn settings_plugin(app: &mut App) {
+    if !app.world.contains_resource::<SettingsPath>() {
+        app.insert_resource(SettingsPath(default_settings_path()));
+    }
+    if !app.world.contains_resource::<Settings>() {
+        let (settings, load_log) =
+            Settings::load_or_default(&app.world.resource::<SettingsPath>().0);
+        app.insert_resource(settings).insert_resource(load_log);
+    }
+    app.init_resource::<PendingSettingsSave>()
+        .init_resource::<SettingsLoadLog>()
+        .add_systems(Startup, log_settings_load)
+        .add_systems(
+            Update,
+            (
+                apply_settings,
+                track_window_settings,
+                track_debug_display_setting,
+                save_settings,
+            )
+                .chain(),
+        )
+        .add_systems(Last, save_settings_on_exit);
+}
+
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct SettingsPath(pub PathBuf);
+
+// What loading the settings ran into. They're usually loaded before the app and its logger exist,
+// so the messages are kept until log_settings_load writes them out at startup
+#[derive(Resource, Default, Debug)]
+pub struct SettingsLoadLog(pub Vec<SettingsNotice>);
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsNotice {
+    Info(String),
+    Warn(String),
+}
+
+// Time left before changed settings are written, none when they're saved
+#[derive(Resource, Default)]
+pub struct PendingSettingsSave(pub Option<Timer>);
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub video: VideoSettings,
+    pub audio: AudioSettings,
+    pub controls: KeyBindings,
+    pub accessibility: AccessibilitySettings,
+    pub gameplay: GameplaySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            video: default(),
+            audio: default(),
+            controls: default(),
+            accessibility: default(),
+            gameplay: default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub mode: WindowMode,
+    // windowed size in logical pixels and position in physical pixels, remembered between launches
+    pub width: f32,
+    pub height: f32,
+    pub position: Option<IVec2>,
+    pub monitor: Option<usize>,
+    pub show_debug_display: bool,
+    // fraction of particles emitted by effects
+    pub particle_density: f32,
+    // most particles alive at once across every effect
+    pub particle_budget: usize,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            mode: WindowMode::Windowed,
+            width: LOGICAL_WIDTH,
+            height: LOGICAL_HEIGHT,
+            position: None,
+            monitor: None,
+            show_debug_display: false,
+            particle_density: 1.,
+            particle_budget: DEFAULT_PARTICLE_BUDGET,
+        }
+    }
+}
+
+// Volumes in 0..=1
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub ui: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 0.6,
+            sfx: 1.,
+            ui: 1.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub screen_shake: bool,
+    pub reduced_effects: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: true,
+            reduced_effects: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GameplaySettings {
+    // write every windowed run to the last replay in the platform data directory, see default_replay_path
+    pub record_replays: bool,
+    // start on the hand placed dev layout instead of an asteroid field
+    pub sandbox: bool,
+    pub flight_model: FlightModel,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            record_replays: false,
+            sandbox: false,
+            flight_model: FlightModel::Assisted,
+        }
+    }
+}
+
+pub fn default_settings_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(dir) => dir.join(SETTINGS_DIR).join(SETTINGS_FILE),
+        None => PathBuf::from(SETTINGS_FILE),
+    }
+}
+
+impl Settings {
+    // Never fails: a missing file gives the defaults, a corrupt one is moved aside to <file>.bak
+    // so it can be inspected, and replaced with the defaults
+    pub fn load_or_default(path: &Path) -> (Self, SettingsLoadLog) {
+        let mut log = SettingsLoadLog::default();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log.info(format!("no settings at {:?}, using defaults", path));
+                return (Self::default(), log);
+            }
+            Err(e) => {
+                log.warn(format!(
+                    "failed to read settings {:?}: {}, using defaults",
+                    path, e
+                ));
+                return (Self::default(), log);
+            }
+        };
+        let settings = match ron::from_str::<Settings>(&text) {
+            Ok(settings) => settings.migrate(&mut log),
+            Err(e) => {
+                let backup = path.with_extension("ron.bak");
+                log.warn(format!(
+                    "corrupt settings {:?}: {}, moved to {:?} and using defaults",
+                    path, e, backup
+                ));
+                if let Err(e) = fs::rename(path, &backup) {
+                    log.warn(format!("failed to move corrupt settings aside: {}", e));
+                }
+                Self::default()
+            }
+        };
+        (settings, log)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // write then rename so a crash mid-write never leaves a truncated file behind
+        let tmp = path.with_extension("ron.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, path)
+    }
+
+    fn migrate(mut self, log: &mut SettingsLoadLog) -> Self {
+        if self.version > SETTINGS_VERSION {
+            log.warn(format!(
+                "settings are from a newer version ({} > {}), unknown fields are ignored",
+                self.version, SETTINGS_VERSION
+            ));
+        }
+        // no schema changes yet, future migrations go here as `if self.version < N { .. }`
+        self.version = SETTINGS_VERSION;
+        self
+    }
+}
+
+impl SettingsLoadLog {
+    fn info(&mut self, message: String) {
+        self.0.push(SettingsNotice::Info(message));
+    }
+
+    fn warn(&mut self, message: String) {
+        self.0.push(SettingsNotice::Warn(message));
+    }
+}
+
+fn log_settings_load(mut load_log: ResMut<SettingsLoadLog>) {
+    for notice in load_log.0.drain(..) {
+        match notice {
+            SettingsNotice::Info(message) => info!("{}", message),
+            SettingsNotice::Warn(message) => warn!("{}", message),
+        }
+    }
+}
+
+// Push settings out to the resources that use them, at startup and whenever they change
+#[allow(clippy::too_many_arguments)]
+pub fn apply_settings(
+    settings: Res<Settings>,
+    key_bindings: Option<ResMut<KeyBindings>>,
+    flight_model: Option<ResMut<FlightModel>>,
+    particle_density: Option<ResMut<ParticleDensity>>,
+    particle_budget: Option<ResMut<ParticleBudget>>,
+    mixer: Option<ResMut<AudioMixer>>,
+    show_debug_display: Option<ResMut<ShowDebugDisplay>>,
+    camera_shake: Option<ResMut<CameraShake>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(mut key_bindings) = key_bindings {
+        key_bindings.set_if_neq(settings.controls);
+    }
+    if let Some(mut flight_model) = flight_model {
+        flight_model.set_if_neq(settings.gameplay.flight_model);
+    }
+    if let Some(mut particle_density) = particle_density {
+        particle_density.set_if_neq(ParticleDensity(settings.video.particle_density));
+    }
+    if let Some(mut particle_budget) = particle_budget {
+        particle_budget.set_if_neq(ParticleBudget {
+            max_particles: settings.video.particle_budget,
+            reduced_effects: settings.accessibility.reduced_effects,
+        });
+    }
+    if let Some(mut mixer) = mixer {
+        mixer.set_if_neq(AudioMixer::from(&settings.audio));
+    }
+    if let Some(mut show_debug_display) = show_debug_display {
+        if **show_debug_display != settings.video.show_debug_display {
+            **show_debug_display = settings.video.show_debug_display;
+        }
+    }
+    if let Some(camera_shake) = camera_shake {
+        camera_shake
+            .map_unchanged(|camera_shake| &mut camera_shake.enabled)
+            .set_if_neq(settings.accessibility.screen_shake);
+    }
+}
+
+// Window changes (F11, size presets, dragging the window around) are remembered for the next launch
+pub fn track_window_settings(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    geometry: Res<WindowGeometry>,
+    mut settings: ResMut<Settings>,
+) {
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+    let mut video = settings.video.clone();
+    video.mode = window.mode;
+    if let Some(size) = geometry.size {
+        video.width = size.x;
+        video.height = size.y;
+    }
+    if geometry.position.is_some() {
+        video.position = geometry.position;
+    }
+    if video != settings.video {
+        settings.video = video;
+    }
+}
+
+pub fn track_debug_display_setting(
+    show_debug_display: Option<Res<ShowDebugDisplay>>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(show_debug_display) = show_debug_display else {
+        return;
+    };
+    if show_debug_display.is_changed() && settings.video.show_debug_display != **show_debug_display
+    {
+        settings.video.show_debug_display = **show_debug_display;
+    }
+}
+
+// Every change restarts the delay, so settings changing every frame are only written once they settle
+pub fn save_settings(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    path: Res<SettingsPath>,
+    mut pending: ResMut<PendingSettingsSave>,
+) {
+    if settings.is_changed() && !settings.is_added() {
+        pending.0 = Some(Timer::from_seconds(SETTINGS_SAVE_DELAY, TimerMode::Once));
+    }
+    let Some(timer) = &mut pending.0 else {
+        return;
+    };
+    if timer.tick(time.delta()).finished() {
+        pending.0 = None;
+        write_settings(&settings, &path);
+    }
+}
+
+pub fn save_settings_on_exit(
+    mut evr_exit: EventReader<AppExit>,
+    settings: Res<Settings>,
+    path: Res<SettingsPath>,
+    mut pending: ResMut<PendingSettingsSave>,
+) {
+    if evr_exit.read().next().is_some() && pending.0.take().is_some() {
+        write_settings(&settings, &path);
+    }
+}
+
+fn write_settings(settings: &Settings, path: &SettingsPath) {
+    match settings.save(path) {
+        Ok(()) => debug!("saved settings {:?}", path.0),
+        Err(e) => error!("failed to save settings {:?}: {}", path.0, e),
+    }
+}
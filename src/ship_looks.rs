@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::{ExternalForce, Velocity};
+use bevy_vector_shapes::{painter::ShapePainter, shapes::DiscPainter};
+
+use crate::{
+    components::PlayerShipTag,
+    game::{INIT_SHIP_MOVE_SPEED, SHIP_LENGTH_AFT, SHIP_THRUST_FORCE_MAGNITUDE},
+    trails::Trail,
+};
+
+// Momentum you can read at a glance, on top of the thruster particles: a trail behind the ship that
+// grows wider and brighter the faster it goes (a Trail, see trails), and a glow at its engine that swells
+// and heats up with thrust and speed. Both are drawn with bevy_vector_shapes every frame from the ship's
+// Transform, Velocity and ExternalForce, gameplay doesn't know about them.
+
+pub const TRAIL_SECS: f32 = 0.6;
+// at INIT_SHIP_MOVE_SPEED, the trail is thinner and fainter the slower the ship goes
+pub const TRAIL_THICKNESS: f32 = 6.;
+pub const TRAIL_COLOR: Color = Color::rgba(0.4, 1., 0.5, 0.35);
+// slower than this leaves no trail
+pub const TRAIL_MIN_SPEED: f32 = 20.;
+// positions the trail remembers, one per frame
+const MAX_TRAIL_POINTS: usize = 90;
+
+pub const GLOW_IDLE_RADIUS: f32 = 3.;
+// added at full thrust, and at INIT_SHIP_MOVE_SPEED
+pub const GLOW_THRUST_RADIUS: f32 = 9.;
+pub const GLOW_SPEED_RADIUS: f32 = 3.;
+pub const GLOW_IDLE_COLOR: Color = Color::rgba(1., 0.3, 0.1, 0.3);
+pub const GLOW_HOT_COLOR: Color = Color::rgba(1., 0.9, 0.8, 0.85);
+// how fast the glow follows thrust, per second
+pub const GLOW_RESPONSE: f32 = 12.;
+
+pub fn ship_looks_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (attach_ship_looks, update_engine_glows, draw_engine_glows).chain(),
+    );
+}
+
+#[derive(Component, Default)]
+pub struct EngineGlow {
+    // 0 idle, 1 at full thrust, eased toward the current thrust
+    pub heat: f32,
+    pub position: Vec2,
+    pub speed: f32,
+}
+
+pub fn attach_ship_looks(
+    mut cmd: Commands,
+    q_ships: Query<Entity, (With<PlayerShipTag>, Without<EngineGlow>)>,
+) {
+    for entity in &q_ships {
+        let trail = Trail::new(TRAIL_COLOR, TRAIL_THICKNESS, TRAIL_SECS, MAX_TRAIL_POINTS)
+            .with_speed(INIT_SHIP_MOVE_SPEED, TRAIL_MIN_SPEED)
+            .with_offset(Vec2::new(0., -SHIP_LENGTH_AFT))
+            .with_z(0.5);
+        cmd.entity(entity).insert((trail, EngineGlow::default()));
+    }
+}
+
+fn engine_position(transform: &Transform) -> Vec2 {
+    transform
+        .transform_point(Vec3::new(0., -SHIP_LENGTH_AFT, 0.))
+        .truncate()
+}
+
+pub fn update_engine_glows(
+    time: Res<Time>,
+    mut q_ships: Query<(&Transform, &Velocity, &ExternalForce, &mut EngineGlow)>,
+) {
+    let easing = (GLOW_RESPONSE * time.delta_seconds()).min(1.);
+    for (transform, velocity, force, mut glow) in &mut q_ships {
+        let thrust = (force.force.length() / SHIP_THRUST_FORCE_MAGNITUDE).clamp(0., 1.);
+        glow.heat += (thrust - glow.heat) * easing;
+        glow.position = engine_position(transform);
+        glow.speed = velocity.linvel.length();
+    }
+}
+
+pub fn draw_engine_glows(mut painter: ShapePainter, q_glows: Query<&EngineGlow>) {
+    for glow in &q_glows {
+        let momentum = (glow.speed / INIT_SHIP_MOVE_SPEED).min(1.5);
+        let radius =
+            GLOW_IDLE_RADIUS + GLOW_THRUST_RADIUS * glow.heat + GLOW_SPEED_RADIUS * momentum;
+        let from = Vec4::from(GLOW_IDLE_COLOR.as_rgba_f32());
+        let to = Vec4::from(GLOW_HOT_COLOR.as_rgba_f32());
+        let color = from.lerp(to, glow.heat);
+        painter.reset();
+        painter.hollow = false;
+        painter.color = Color::rgba(color.x, color.y, color.z, color.w);
+        painter.translate(glow.position.extend(0.6));
+        painter.circle(radius);
+        // a hotter core
+        painter.color = GLOW_HOT_COLOR.with_a(GLOW_HOT_COLOR.a() * glow.heat);
+        painter.circle(radius * 0.4);
+    }
+}
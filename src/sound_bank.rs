@@ -0,0 +1,154 @@
+use bevy::{asset::LoadContext, prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{
+    audio::AudioBus,
+    ron_asset::{RonAsset, RonAssetError, RonAssetLoader},
+};
+
+// Which clips play for which gameplay sound, and how. Loaded from assets/sounds/default.soundbank.ron,
+// see ron_asset.
+pub const SOUND_BANK_PATH: &str = "sounds/default.soundbank.ron";
+
+// Gameplay events that make a sound, the keys of the sound bank
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    ProjectileFired,
+    ProjectileHitAsteroid,
+    ProjectileHit,
+    AsteroidClashSmall,
+    AsteroidClashMedium,
+    AsteroidClashLarge,
+    AsteroidDestroyed,
+    ShipDamaged,
+    ShipDestroyed,
+    UiClick,
+    StingerShipDestroyed,
+    StingerFieldCleared,
+}
+
+impl Sound {
+    pub fn bus(self) -> AudioBus {
+        match self {
+            Sound::UiClick => AudioBus::Ui,
+            Sound::StingerShipDestroyed | Sound::StingerFieldCleared => AudioBus::Music,
+            _ => AudioBus::Sfx,
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct SoundBank {
+    pub sounds: HashMap<Sound, SoundEntry>,
+}
+
+impl SoundBank {
+    pub fn get(&self, sound: Sound) -> Option<&SoundEntry> {
+        self.sounds.get(&sound)
+    }
+}
+
+#[derive(Debug)]
+pub struct SoundEntry {
+    // one is picked at random every time the sound plays
+    pub clips: Vec<Handle<AudioSource>>,
+    pub volume: f32,
+    pub pitch: f32,
+    // each play is off by up to this much, up or down
+    pub volume_variation: f32,
+    pub pitch_variation: f32,
+    // voices of this sound playing at once, the oldest is cut off to start another
+    pub max_voices: usize,
+    // when all voices are taken, a sound may cut off a voice of the same or lower priority
+    pub priority: u8,
+    // starts closer together than this are dropped
+    pub min_interval_secs: f32,
+}
+
+// SoundEntry as written in the file, with clip paths instead of handles
+#[derive(Deserialize)]
+#[serde(default)]
+struct SoundEntryDef {
+    clips: Vec<String>,
+    volume: f32,
+    pitch: f32,
+    volume_variation: f32,
+    pitch_variation: f32,
+    max_voices: usize,
+    priority: u8,
+    min_interval_secs: f32,
+}
+
+impl Default for SoundEntryDef {
+    fn default() -> Self {
+        Self {
+            clips: Vec::new(),
+            volume: 1.,
+            pitch: 1.,
+            volume_variation: 0.,
+            pitch_variation: 0.,
+            max_voices: 4,
+            priority: 1,
+            min_interval_secs: 0.,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SoundBankDef {
+    sounds: HashMap<Sound, SoundEntryDef>,
+}
+
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct SoundBankHandle(pub Handle<SoundBank>);
+
+impl RonAsset for SoundBank {
+    type Def = SoundBankDef;
+
+    const EXTENSIONS: &'static [&'static str] = &["soundbank.ron"];
+
+    fn from_def(def: SoundBankDef, load_context: &mut LoadContext) -> Result<Self, RonAssetError> {
+        let sounds = def
+            .sounds
+            .into_iter()
+            .map(|(sound, entry)| {
+                let clips = entry
+                    .clips
+                    .into_iter()
+                    .map(|path| load_context.load(path))
+                    .collect();
+                let entry = SoundEntry {
+                    clips,
+                    volume: entry.volume.max(0.),
+                    pitch: entry.pitch.max(0.01),
+                    volume_variation: entry.volume_variation.abs(),
+                    pitch_variation: entry.pitch_variation.abs(),
+                    max_voices: entry.max_voices,
+                    priority: entry.priority,
+                    min_interval_secs: entry.min_interval_secs,
+                };
+                (sound, entry)
+            })
+            .collect();
+        Ok(SoundBank { sounds })
+    }
+}
+
+pub fn sound_bank_plugin(app: &mut App) {
+    app.init_asset::<SoundBank>()
+        .init_asset_loader::<RonAssetLoader<SoundBank>>()
+        .add_systems(Startup, load_sound_bank)
+        .add_systems(Update, log_sound_bank_reloads);
+}
+
+pub fn load_sound_bank(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(SoundBankHandle(asset_server.load(SOUND_BANK_PATH)));
+}
+
+pub fn log_sound_bank_reloads(mut evr_assets: EventReader<AssetEvent<SoundBank>>) {
+    for event in evr_assets.read() {
+        if let AssetEvent::Modified { .. } = event {
+            info!("reloaded sound bank");
+        }
+    }
+}
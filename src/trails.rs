@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+use bevy_vector_shapes::{painter::ShapePainter, shapes::LinePainter};
+
+use crate::game::{LOGICAL_HEIGHT, LOGICAL_WIDTH};
+
+// Lines left behind moving things, e.g. projectiles (projectile_looks) and the ship (ship_looks). A Trail
+// remembers where its entity went, one point per frame, and is drawn with bevy_vector_shapes as segments
+// that fade and thin out with age. Trails can also follow speed, getting wider and brighter the faster
+// their entity went.
+
+pub fn trails_plugin(app: &mut App) {
+    app.add_systems(Update, (record_trails, draw_trails).chain());
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Trail {
+    pub color: Color,
+    // at the newest point, the trail tapers to nothing at its end
+    pub thickness: f32,
+    // how long a point stays on the trail
+    pub duration: f32,
+    pub max_points: usize,
+    // speed at which the trail is as thick and bright as given, none when speed doesn't matter
+    pub full_speed: Option<f32>,
+    // slower than this leaves no trail
+    pub min_speed: f32,
+    // where the trail starts in the entity's own space, e.g. at the ship's engine
+    pub offset: Vec2,
+    pub z: f32,
+    pub points: VecDeque<TrailPoint>,
+}
+
+// Newest first on the trail
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrailPoint {
+    pub at: f32,
+    pub position: Vec2,
+    pub speed: f32,
+}
+
+impl Trail {
+    pub fn new(color: Color, thickness: f32, duration: f32, max_points: usize) -> Self {
+        Self {
+            color,
+            thickness,
+            duration,
+            max_points,
+            full_speed: None,
+            min_speed: 0.,
+            offset: Vec2::ZERO,
+            z: 0.,
+            points: VecDeque::with_capacity(max_points),
+        }
+    }
+
+    pub fn with_speed(mut self, full_speed: f32, min_speed: f32) -> Self {
+        self.full_speed = Some(full_speed);
+        self.min_speed = min_speed;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    // A jump of more than half the arena in a frame is wrapping around it, the trail starts over there
+    // instead of drawing a line across the arena
+    pub fn record(&mut self, point: TrailPoint) {
+        if let Some(last) = self.points.front() {
+            let jump = (point.position - last.position).abs();
+            if jump.x > LOGICAL_WIDTH / 2. || jump.y > LOGICAL_HEIGHT / 2. {
+                self.points.clear();
+            }
+        }
+        self.points.push_front(point);
+        while self.points.len() > self.max_points
+            || self
+                .points
+                .back()
+                .is_some_and(|last| point.at - last.at > self.duration)
+        {
+            self.points.pop_back();
+        }
+    }
+}
+
+// Trails go on entities without a parent, their Transform is already where they are in the world, and
+// unlike GlobalTransform it's there the frame they're spawned
+pub fn record_trails(
+    time: Res<Time>,
+    mut q_trails: Query<(&Transform, Option<&Velocity>, &mut Trail)>,
+) {
+    let now = time.elapsed_seconds();
+    for (transform, velocity, mut trail) in &mut q_trails {
+        let position = transform
+            .transform_point(trail.offset.extend(0.))
+            .truncate();
+        let speed = velocity.map_or(0., |velocity| velocity.linvel.length());
+        trail.record(TrailPoint {
+            at: now,
+            position,
+            speed,
+        });
+    }
+}
+
+pub fn draw_trails(time: Res<Time>, mut painter: ShapePainter, q_trails: Query<&Trail>) {
+    let now = time.elapsed_seconds();
+    for trail in &q_trails {
+        let segments = trail.points.iter().zip(trail.points.iter().skip(1));
+        for (from, to) in segments {
+            if from.speed < trail.min_speed {
+                continue;
+            }
+            let age = 1. - ((now - from.at) / trail.duration).clamp(0., 1.);
+            let momentum = trail
+                .full_speed
+                .map_or(1., |full_speed| (from.speed / full_speed).min(1.5));
+            painter.thickness = trail.thickness * momentum * age;
+            painter.color = trail.color.with_a(trail.color.a() * momentum.min(1.) * age);
+            painter.line(from.position.extend(trail.z), to.position.extend(trail.z));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(at: f32, x: f32, y: f32) -> TrailPoint {
+        TrailPoint {
+            at,
+            position: Vec2::new(x, y),
+            speed: 0.,
+        }
+    }
+
+    #[test]
+    fn forgets_old_points() {
+        let mut trail = Trail::new(Color::WHITE, 1., 0.5, 3);
+        for i in 0..5 {
+            trail.record(point(i as f32 * 0.1, i as f32, 0.));
+        }
+        assert_eq!(trail.points.len(), 3);
+        trail.record(point(2., 10., 0.));
+        assert_eq!(trail.points, [point(2., 10., 0.)]);
+    }
+
+    #[test]
+    fn starts_over_on_wraparound() {
+        let mut trail = Trail::new(Color::WHITE, 1., 1., 10);
+        trail.record(point(0., 0., LOGICAL_HEIGHT / 2. - 5.));
+        trail.record(point(0.1, 0., LOGICAL_HEIGHT / 2. - 1.));
+        assert_eq!(trail.points.len(), 2);
+        trail.record(point(0.2, 0., -LOGICAL_HEIGHT / 2. + 3.));
+        assert_eq!(trail.points, [point(0.2, 0., -LOGICAL_HEIGHT / 2. + 3.)]);
+        trail.record(point(0.3, LOGICAL_WIDTH / 2. - 1., 0.));
+        trail.record(point(0.4, -LOGICAL_WIDTH / 2. + 1., 0.));
+        assert_eq!(trail.points.len(), 1);
+    }
+}
@@ -42,13 +42,13 @@ impl Heading {
 
 impl Default for Heading {
     fn default() -> Self {
-        DEFAULT_HEADING.clone()
+        *DEFAULT_HEADING
     }
 }
 
-impl Into<Quat> for Heading {
-    fn into(self) -> Quat {
-        let angle_radians = self.0.to_radians() - PI / 2.0;
+impl From<Heading> for Quat {
+    fn from(heading: Heading) -> Self {
+        let angle_radians = heading.0.to_radians() - PI / 2.0;
         Quat::from_rotation_z(angle_radians)
     }
 }
@@ -0,0 +1,278 @@
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+use bevy_rapier2d::dynamics::Velocity;
+
+use aion_bevy::{
+    archetypes::{AsteroidBundle, ProjectileBundle},
+    avatars::PlayerShip,
+    components::{DespawnDelay, Health, Score},
+    effects::{CollisionEffectEvent, DestructionEffectEvent},
+    events::Avatars,
+    game::{PlayerShipTexture, MEDIUM_ASTEROID_R},
+    headless::{clear_play_layout, headless_app, run_fixed_steps},
+    utils::Heading,
+};
+
+// Generous upper bound on ticks for two bodies a few dozen pixels apart to touch
+const MAX_TICKS: u32 = 128;
+
+// Headless app with the default play layout cleared, recording every effect event handle_collisions emits
+struct Arena {
+    app: App,
+    collision_reader: ManualEventReader<CollisionEffectEvent>,
+    destruction_reader: ManualEventReader<DestructionEffectEvent>,
+    collisions: Vec<CollisionEffectEvent>,
+    destructions: Vec<DestructionEffectEvent>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        let mut app = headless_app(0);
+        run_fixed_steps(&mut app, 0);
+        clear_play_layout(&mut app);
+
+        let collision_reader = app
+            .world
+            .resource::<Events<CollisionEffectEvent>>()
+            .get_reader_current();
+        let destruction_reader = app
+            .world
+            .resource::<Events<DestructionEffectEvent>>()
+            .get_reader_current();
+
+        Self {
+            app,
+            collision_reader,
+            destruction_reader,
+            collisions: vec![],
+            destructions: vec![],
+        }
+    }
+
+    fn spawn_asteroid(&mut self, x: f32, y: f32, heading: Heading, speed: f32, health: i32) -> Entity {
+        let velocity = Velocity {
+            linvel: heading.linvel(speed),
+            angvel: 0.,
+        };
+        self.app
+            .world
+            .spawn(AsteroidBundle::<ColorMaterial>::new(
+                Handle::default(),
+                Handle::default(),
+                MEDIUM_ASTEROID_R,
+                x,
+                y,
+                Some(velocity),
+                Some(health),
+            ))
+            .id()
+    }
+
+    fn spawn_projectile(&mut self, x: f32, y: f32, heading: Heading) -> Entity {
+        self.app
+            .world
+            .spawn(ProjectileBundle::new(
+                x,
+                y,
+                Some(heading),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .id()
+    }
+
+    fn spawn_ship(&mut self, x: f32, y: f32, health: i32) -> Entity {
+        let (ship, (emitter, thruster)) =
+            PlayerShip::new(x, y, None, &PlayerShipTexture(Handle::default()));
+        self.app
+            .world
+            .spawn(ship)
+            .insert(Health(health))
+            .with_children(|parent| {
+                parent.spawn(emitter);
+                parent.spawn(thruster);
+            })
+            .id()
+    }
+
+    fn step(&mut self) {
+        run_fixed_steps(&mut self.app, 1);
+        let events = self.app.world.resource::<Events<CollisionEffectEvent>>();
+        self.collisions
+            .extend(self.collision_reader.read(events).cloned());
+        let events = self.app.world.resource::<Events<DestructionEffectEvent>>();
+        self.destructions
+            .extend(self.destruction_reader.read(events).cloned());
+    }
+
+    // Steps until an effect event is emitted, then one more tick so deferred despawns are applied
+    fn step_until_impact(&mut self) {
+        for _ in 0..MAX_TICKS {
+            self.step();
+            if !self.collisions.is_empty() || !self.destructions.is_empty() {
+                self.step();
+                return;
+            }
+        }
+        panic!("no collision within {MAX_TICKS} ticks");
+    }
+
+    fn health(&self, entity: Entity) -> i32 {
+        **self.app.world.get::<Health>(entity).expect("entity has health")
+    }
+
+    fn is_despawned(&self, entity: Entity) -> bool {
+        self.app.world.get_entity(entity).is_none()
+    }
+
+    fn score(&self) -> usize {
+        **self.app.world.resource::<Score>()
+    }
+
+    fn count_collisions(&self, avatar: Avatars) -> usize {
+        self.collisions
+            .iter()
+            .filter(|event| event.avatar_a == avatar)
+            .count()
+    }
+
+    fn count_destructions(&self, avatar: Avatars) -> usize {
+        self.destructions
+            .iter()
+            .filter(|event| event.avatar == avatar)
+            .count()
+    }
+}
+
+#[test]
+fn projectile_damages_asteroid() {
+    let mut arena = Arena::new();
+    let aster = arena.spawn_asteroid(0., 0., Heading(0.), 0., 3);
+    let proj = arena.spawn_projectile(-60., 0., Heading(0.));
+
+    arena.step_until_impact();
+
+    assert_eq!(arena.health(aster), 2);
+    assert_eq!(arena.score(), 0);
+    assert_eq!(arena.count_collisions(Avatars::Projectile), 1);
+    assert!(arena.destructions.is_empty());
+    assert!(arena.app.world.get::<DespawnDelay>(proj).is_some());
+}
+
+#[test]
+fn projectile_destroys_asteroid() {
+    let mut arena = Arena::new();
+    let aster = arena.spawn_asteroid(0., 0., Heading(0.), 0., 1);
+    arena.spawn_projectile(-60., 0., Heading(0.));
+
+    arena.step_until_impact();
+
+    assert!(arena.is_despawned(aster));
+    assert_eq!(arena.score(), 1);
+    assert_eq!(arena.count_collisions(Avatars::Projectile), 1);
+    assert_eq!(arena.count_destructions(Avatars::Asteroid), 1);
+}
+
+#[test]
+fn projectile_damages_ship() {
+    let mut arena = Arena::new();
+    let ship = arena.spawn_ship(0., 0., 3);
+    arena.spawn_projectile(0., 60., Heading(-90.));
+
+    arena.step_until_impact();
+
+    assert_eq!(arena.health(ship), 2);
+    assert_eq!(arena.count_collisions(Avatars::Projectile), 1);
+    assert_eq!(arena.count_collisions(Avatars::PlayerShip), 1);
+    assert!(arena.destructions.is_empty());
+}
+
+#[test]
+fn projectile_destroys_ship() {
+    let mut arena = Arena::new();
+    let ship = arena.spawn_ship(0., 0., 1);
+    arena.spawn_projectile(0., 60., Heading(-90.));
+
+    arena.step_until_impact();
+
+    assert!(arena.is_despawned(ship));
+    assert_eq!(arena.count_collisions(Avatars::PlayerShip), 0);
+    assert_eq!(arena.count_destructions(Avatars::PlayerShip), 1);
+    assert_eq!(arena.score(), 0);
+}
+
+#[test]
+fn asteroids_clash_without_damage() {
+    let mut arena = Arena::new();
+    let aster_a = arena.spawn_asteroid(-100., 0., Heading(0.), 200., 3);
+    let aster_b = arena.spawn_asteroid(100., 0., Heading(180.), 200., 3);
+
+    arena.step_until_impact();
+
+    assert_eq!(arena.health(aster_a), 3);
+    assert_eq!(arena.health(aster_b), 3);
+    assert_eq!(arena.count_collisions(Avatars::Asteroid), 1);
+    let clash = &arena.collisions[0];
+    assert_eq!(clash.avatar_b, Some(Avatars::Asteroid));
+    assert!(clash.transform_b.is_some());
+    assert!(clash.collision_radius_a.is_some());
+    assert!(arena.destructions.is_empty());
+}
+
+#[test]
+fn asteroid_damages_ship() {
+    let mut arena = Arena::new();
+    let ship = arena.spawn_ship(0., 0., 3);
+    let aster = arena.spawn_asteroid(0., 100., Heading(-90.), 200., 3);
+
+    arena.step_until_impact();
+
+    assert_eq!(arena.health(ship), 2);
+    assert_eq!(arena.health(aster), 3);
+    assert_eq!(arena.count_collisions(Avatars::PlayerShip), 1);
+    assert!(arena.destructions.is_empty());
+}
+
+#[test]
+fn asteroid_destroys_ship() {
+    let mut arena = Arena::new();
+    let ship = arena.spawn_ship(0., 0., 1);
+    arena.spawn_asteroid(0., 100., Heading(-90.), 200., 3);
+
+    arena.step_until_impact();
+
+    assert!(arena.is_despawned(ship));
+    assert_eq!(arena.count_destructions(Avatars::PlayerShip), 1);
+    assert_eq!(arena.count_collisions(Avatars::PlayerShip), 0);
+}
+
+#[test]
+fn simultaneous_hits_destroy_asteroid_once() {
+    let mut arena = Arena::new();
+    let aster = arena.spawn_asteroid(0., 0., Heading(0.), 0., 1);
+    arena.spawn_projectile(-60., 0., Heading(0.));
+    arena.spawn_projectile(60., 0., Heading(180.));
+
+    arena.step_until_impact();
+
+    assert!(arena.is_despawned(aster));
+    assert_eq!(arena.count_collisions(Avatars::Projectile), 2);
+    assert_eq!(arena.count_destructions(Avatars::Asteroid), 1);
+    assert_eq!(arena.score(), 1);
+}
+
+#[test]
+fn simultaneous_hits_accumulate_damage() {
+    let mut arena = Arena::new();
+    let aster = arena.spawn_asteroid(0., 0., Heading(0.), 0., 3);
+    arena.spawn_projectile(-60., 0., Heading(0.));
+    arena.spawn_projectile(60., 0., Heading(180.));
+
+    arena.step_until_impact();
+
+    assert_eq!(arena.health(aster), 1);
+    assert_eq!(arena.count_collisions(Avatars::Projectile), 2);
+    assert!(arena.destructions.is_empty());
+    assert_eq!(arena.score(), 0);
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+
+use aion_bevy::{
+    archetypes::{AsteroidBundle, ProjectileBundle},
+    components::Score,
+    effects::{emit_asteroid_debris, fade_debris, Debris, DestructionEffectEvent},
+    game::MEDIUM_ASTEROID_R,
+    headless::{clear_play_layout, headless_app, run_fixed_steps},
+    replay::{ReplayMode, ReplayPlayback, ReplayRecorder, DEFAULT_CHECKSUM_INTERVAL},
+    utils::Heading,
+};
+
+// Long enough for the asteroid to be shot down and a few checksums to be taken after that
+const N_TICKS: u32 = 4 * DEFAULT_CHECKSUM_INTERVAL;
+const MAX_TICKS_TO_KILL: u32 = 128;
+
+// The debris handle_destruction_effects emits in a windowed run, without the particles
+fn emit_debris(mut cmd: Commands, mut evr_destructions: EventReader<DestructionEffectEvent>) {
+    for event in evr_destructions.read() {
+        let radius = event.collision_radius.map_or(MEDIUM_ASTEROID_R, |r| r.0);
+        let velocity = event.velocity.unwrap_or_default();
+        emit_asteroid_debris(&mut cmd, &event.transform, radius, &velocity, Color::WHITE);
+    }
+}
+
+// Headless run with the default play layout cleared, an asteroid shot down by a projectile, and
+// optionally debris flying off it the way effects spawn it in a windowed run
+fn shoot_down_asteroid(mode: ReplayMode, with_debris: bool) -> App {
+    let mut app = headless_app(0);
+    app.insert_resource(mode);
+    if with_debris {
+        app.add_systems(Update, (emit_debris, fade_debris));
+    }
+    run_fixed_steps(&mut app, 0);
+    clear_play_layout(&mut app);
+
+    let asteroid = app
+        .world
+        .spawn(AsteroidBundle::<ColorMaterial>::new(
+            Handle::default(),
+            Handle::default(),
+            MEDIUM_ASTEROID_R,
+            0.,
+            0.,
+            Some(Velocity::zero()),
+            Some(1),
+        ))
+        .id();
+    app.world.spawn(ProjectileBundle::new(
+        -60.,
+        0.,
+        Some(Heading(0.)),
+        None,
+        None,
+        None,
+        None,
+    ));
+
+    let mut n_ticks = 1;
+    while app.world.get_entity(asteroid).is_some() {
+        assert!(n_ticks < MAX_TICKS_TO_KILL, "asteroid never destroyed");
+        run_fixed_steps(&mut app, 1);
+        n_ticks += 1;
+    }
+    let n_debris = app.world.query::<&Debris>().iter(&app.world).count();
+    assert_eq!(n_debris > 0, with_debris);
+    run_fixed_steps(&mut app, N_TICKS - n_ticks);
+    assert_eq!(**app.world.resource::<Score>(), 1);
+    app
+}
+
+#[test]
+fn debris_does_not_desync_playback() {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("aion-debris-{}.aionreplay", std::process::id()));
+
+    let recorded = shoot_down_asteroid(ReplayMode::Record(path.clone()), false);
+    let replay = &recorded.world.resource::<ReplayRecorder>().replay;
+    assert_eq!(replay.inputs.len(), N_TICKS as usize);
+    replay.save(&path).unwrap();
+
+    // recording with debris flying around takes the same checksums
+    let recorded_with_debris = shoot_down_asteroid(ReplayMode::Record(path.clone()), true);
+    let replay_with_debris = &recorded_with_debris
+        .world
+        .resource::<ReplayRecorder>()
+        .replay;
+    assert_eq!(replay_with_debris.checksums, replay.checksums);
+
+    let played = shoot_down_asteroid(ReplayMode::Playback(path.clone()), true);
+    let _ = std::fs::remove_file(&path);
+
+    let playback = played.world.resource::<ReplayPlayback>();
+    assert_eq!(playback.replay.checksums.len(), 4);
+    assert!(!playback.is_desynced);
+}